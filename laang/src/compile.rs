@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{
+	read_lines, CliOptions, EvalError, Expression, Fragment, Line, Parser, Statement, Tokenizer,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Target {
+	Sh,
+	Rust,
+}
+
+impl FromStr for Target {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"sh" => Ok(Target::Sh),
+			"rust" => Ok(Target::Rust),
+			_ => Err(format!("Unknown target: {}", s)),
+		}
+	}
+}
+
+/// Writes a standalone program for the target that prints what evaluating the script would.
+pub fn compile<W: Write>(opts: &mut CliOptions<W>, target: Target) -> Result<(), EvalError> {
+	let mut tokenizer = Tokenizer::new();
+	for line in read_lines(Path::new(&opts.path))?.iter() {
+		tokenizer.take_line(line);
+	}
+	let program = Parser::parse(&tokenizer.tokens);
+	let source = Lowering::new(target).lower(&program)?;
+	opts.stdout.write_all(source.as_bytes())?;
+	Ok(())
+}
+
+#[derive(Debug)]
+enum Part {
+	Literal(String),
+	Var(String),
+}
+
+#[derive(Debug)]
+struct Lowering {
+	target: Target,
+	/// Definition names are arbitrary graphemes, so each gets a variable that the target accepts.
+	vars: HashMap<String, String>,
+}
+
+impl Lowering {
+	fn new(target: Target) -> Self {
+		Self {
+			target,
+			vars: HashMap::new(),
+		}
+	}
+
+	fn lower(&mut self, program: &[Line]) -> Result<String, EvalError> {
+		let mut source = self.prelude();
+		for line in program.iter() {
+			match &line.statement {
+				Statement::Assign(t) => {
+					let parts = self.parts(&line.expressions)?;
+					let next = format!("v{}", self.vars.len());
+					let var = self.vars.entry(t.content().to_string()).or_insert(next);
+					source.push_str(&assign(self.target, t.content(), var, &parts));
+				}
+				Statement::Define(t, _) => {
+					return Err(EvalError::Unsupported(format!(
+						"compiling function {}",
+						t.content()
+					)))
+				}
+				#[cfg(feature = "term")]
+				Statement::Call(t) if crate::term::BUILTINS.contains(&t.content()) => {
+					return Err(EvalError::Unsupported(format!(
+						"compiling terminal builtin {}",
+						t.content()
+					)))
+				}
+				Statement::Call(t) if t.content() == "print" => {
+					let parts = self.parts(&line.expressions)?;
+					source.push_str(&print(self.target, &parts));
+				}
+				// Calls to anything else do nothing when evaluated either.
+				Statement::Call(_) | Statement::Empty => {}
+			}
+		}
+		source.push_str(self.epilogue());
+		Ok(source)
+	}
+
+	fn parts(&self, expressions: &[Expression]) -> Result<Vec<Part>, EvalError> {
+		let mut parts = Vec::new();
+		for expr in expressions.iter() {
+			match expr {
+				Expression::Collection(fragments) => {
+					for fragment in fragments.iter() {
+						match fragment {
+							Fragment::Text(token) => push_literal(&mut parts, token.content()),
+							Fragment::Splice(expressions) => {
+								for part in self.parts(expressions)? {
+									match part {
+										Part::Literal(s) => push_literal(&mut parts, &s),
+										var => parts.push(var),
+									}
+								}
+							}
+						}
+					}
+				}
+				Expression::Lookup(t) => {
+					let var = self
+						.vars
+						.get(t.content())
+						.ok_or_else(|| EvalError::InvalidLookup(t.content().to_string()))?;
+					parts.push(Part::Var(var.clone()));
+				}
+			}
+		}
+		Ok(parts)
+	}
+
+	fn prelude(&self) -> String {
+		match self.target {
+			Target::Sh => "#!/bin/sh\n".to_string(),
+			Target::Rust => "#[allow(unused_variables)]\nfn main() {\n".to_string(),
+		}
+	}
+
+	fn epilogue(&self) -> &'static str {
+		match self.target {
+			Target::Sh => "",
+			Target::Rust => "}\n",
+		}
+	}
+}
+
+/// Adds to the previous literal so collections come out as whole strings.
+fn push_literal(parts: &mut Vec<Part>, s: &str) {
+	if let Some(Part::Literal(previous)) = parts.last_mut() {
+		previous.push_str(s);
+	} else {
+		parts.push(Part::Literal(s.to_string()));
+	}
+}
+
+fn assign(target: Target, name: &str, var: &str, parts: &[Part]) -> String {
+	match target {
+		Target::Sh => format!("# {}\n{}={}\n", name, var, sh_word(parts)),
+		Target::Rust => format!(
+			"\t// {}\n\tlet {}: String = {};\n",
+			name,
+			var,
+			rust_string(parts)
+		),
+	}
+}
+
+fn print(target: Target, parts: &[Part]) -> String {
+	match target {
+		Target::Sh => format!("printf '%s\\n' {}\n", sh_word(parts)),
+		Target::Rust => format!("\tprintln!(\"{{}}\", {});\n", rust_string(parts)),
+	}
+}
+
+fn sh_word(parts: &[Part]) -> String {
+	if parts.is_empty() {
+		return "''".to_string();
+	}
+	parts
+		.iter()
+		.map(|part| match part {
+			Part::Literal(s) => format!("'{}'", s.replace('\'', "'\\''")),
+			Part::Var(var) => format!("\"${{{}}}\"", var),
+		})
+		.collect()
+}
+
+fn rust_string(parts: &[Part]) -> String {
+	if parts.is_empty() {
+		return "String::new()".to_string();
+	}
+	let strs = parts
+		.iter()
+		.map(|part| match part {
+			Part::Literal(s) => format!("{:?}", s),
+			Part::Var(var) => format!("{}.as_str()", var),
+		})
+		.collect::<Vec<String>>();
+	format!("[{}].concat()", strs.join(", "))
+}
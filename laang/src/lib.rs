@@ -1,49 +1,129 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::io::{BufRead, Write};
 use std::mem::discriminant;
 use std::path::Path;
+use std::time::Instant;
 
 use lazy_static::lazy_static;
 use regex::Regex;
 use unicode_segmentation::UnicodeSegmentation;
 
+mod compile;
+mod profile;
+#[cfg(feature = "term")]
+mod term;
+
+pub use compile::{compile, Target};
+pub use profile::Profile;
+
+use profile::Profiler;
+
 #[derive(Debug)]
 pub struct CliOptions<W: Write> {
 	pub path: String,
 	pub stdout: W,
+	pub limits: Limits,
+	pub profile: Option<Profile>,
 }
 
-pub fn eval<W: Write>(opts: &mut CliOptions<W>) {
-	let fpath = Path::new(&opts.path);
-	println!("Path: {:?}", fpath);
+/// Calls nest on the stack, so they are never allowed deeper than this, whatever the limits say.
+pub const MAX_DEPTH: usize = 1000;
 
-	let f = fs::File::open(fpath).unwrap();
-	let reader = io::BufReader::new(f);
-	let mut tokenizer = Tokenizer::new();
+/// Stack left below which a call moves on to a new stack segment.
+const STACK_RED_ZONE: usize = 256 * 1024;
 
-	println!("---------");
-	println!("Contents:");
+/// Size of the stack segments calls grow into, enough for a good number of nested calls.
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
 
-	for (i, line_r) in reader.lines().enumerate() {
-		if let Ok(line) = line_r {
-			println!("{}:\t{}", i, line);
-			tokenizer.take_line(&line);
+/// Bounds on the work a script may do. `None` means unlimited.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Limits {
+	/// Tokens the evaluator may process, including those in function bodies.
+	pub max_steps: Option<usize>,
+	/// Nested user function calls, at most and by default [MAX_DEPTH].
+	pub max_depth: Option<usize>,
+	/// Bytes written to `stdout`.
+	pub max_output: Option<usize>,
+	/// Bytes held in definitions and function bodies.
+	pub max_memory: Option<usize>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Limit {
+	Steps(usize),
+	Depth(usize),
+	Output(usize),
+	Memory(usize),
+}
+
+#[derive(Debug)]
+pub enum EvalError {
+	LimitExceeded(Limit),
+	InvalidLookup(String),
+	InvalidArgument(String),
+	Unsupported(String),
+	Io(io::Error),
+	#[cfg(feature = "term")]
+	Term(crossterm::ErrorKind),
+}
+
+impl fmt::Display for EvalError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			EvalError::LimitExceeded(Limit::Steps(max)) => write!(f, "Exceeded {} steps", max),
+			EvalError::LimitExceeded(Limit::Depth(max)) => write!(f, "Exceeded call depth of {}", max),
+			EvalError::LimitExceeded(Limit::Output(max)) => write!(f, "Exceeded {} bytes of output", max),
+			EvalError::LimitExceeded(Limit::Memory(max)) => {
+				write!(f, "Exceeded {} bytes of definitions", max)
+			}
+			EvalError::InvalidLookup(name) => write!(f, "Invalid lookup: {}", name),
+			EvalError::InvalidArgument(what) => write!(f, "Invalid argument: {}", what),
+			EvalError::Unsupported(what) => write!(f, "Unsupported: {}", what),
+			EvalError::Io(e) => write!(f, "{}", e),
+			#[cfg(feature = "term")]
+			EvalError::Term(e) => write!(f, "{}", e),
 		}
 	}
-	println!("---------");
-	println!("Tokens:");
-	println!("{:?}", tokenizer);
+}
 
-	println!("---------");
-	println!("Result:");
+impl From<io::Error> for EvalError {
+	fn from(e: io::Error) -> Self {
+		EvalError::Io(e)
+	}
+}
 
-	Evaluator::new(opts).eval(tokenizer.tokens);
+#[cfg(feature = "term")]
+impl From<crossterm::ErrorKind> for EvalError {
+	fn from(e: crossterm::ErrorKind) -> Self {
+		EvalError::Term(e)
+	}
+}
+
+pub fn eval<W: Write>(opts: &mut CliOptions<W>) -> Result<(), EvalError> {
+	let lines = read_lines(Path::new(&opts.path))?;
+	let mut tokenizer = Tokenizer::new();
+	for line in lines.iter() {
+		tokenizer.take_line(line);
+	}
+
+	let mut evaluator = Evaluator::new(opts);
+	let result = evaluator.eval(&tokenizer.tokens);
+	evaluator.report()?;
+	result
+}
+
+fn read_lines(fpath: &Path) -> io::Result<Vec<String>> {
+	let f = fs::File::open(fpath)?;
+	let reader = io::BufReader::new(f);
+	reader.lines().collect()
 }
 
 lazy_static! {
 	static ref BRACKET: Regex = Regex::new(r"[\[\]]").unwrap();
+	static ref BRACE: Regex = Regex::new(r"[{}]").unwrap();
 	static ref SPACE: Regex = Regex::new(r"[\t ]+").unwrap();
 	static ref BREAK: Regex = Regex::new(r"[\n\r]+").unwrap();
 }
@@ -51,6 +131,7 @@ lazy_static! {
 #[derive(Debug, Copy, Clone)]
 enum TokenType {
 	Bracket,
+	Brace,
 	Space,
 	Break,
 	Text,
@@ -110,6 +191,14 @@ impl Token {
 	fn is_close(&self) -> bool {
 		self.content() == "]"
 	}
+
+	fn is_splice_open(&self) -> bool {
+		self.content() == "{"
+	}
+
+	fn is_splice_close(&self) -> bool {
+		self.content() == "}"
+	}
 }
 
 #[derive(Debug)]
@@ -130,6 +219,8 @@ impl Tokenizer {
 		for g in gs {
 			let current = if BRACKET.is_match(g) {
 				Token::Single(TokenType::Bracket, g.to_string())
+			} else if BRACE.is_match(g) {
+				Token::Single(TokenType::Brace, g.to_string())
 			} else if SPACE.is_match(g) {
 				Token::Multi(TokenType::Space, g.to_string())
 			} else if BREAK.is_match(g) {
@@ -161,29 +252,261 @@ impl Tokenizer {
 enum Statement {
 	Empty,
 	Assign(Token),
+	Define(Token, Vec<Token>),
 	Call(Token),
 }
 
+impl Statement {
+	fn name(&self) -> &str {
+		match self {
+			Statement::Assign(t) | Statement::Define(t, _) | Statement::Call(t) => t.content(),
+			Statement::Empty => "",
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 enum Expression {
-	Empty,
 	Lookup(Token),
-	Collection(Vec<Token>),
+	Collection(Vec<Fragment>),
+}
+
+/// Part of a collection: either literal text or expressions spliced in with braces.
+#[derive(Debug, Clone)]
+enum Fragment {
+	Text(Token),
+	Splice(Vec<Expression>),
 }
 
 #[derive(Debug, Clone)]
 enum Phase {
 	Statement,
+	Params,
 	Expressions,
+	Body,
 }
 
+/// A statement with everything that followed it up to the break.
 #[derive(Debug)]
-struct Evaluator<'opts, W: Write> {
-	defs: HashMap<String, String>,
+struct Line {
+	statement: Statement,
+	expressions: Vec<Expression>,
+	body: Vec<Token>,
+}
+
+#[derive(Debug)]
+struct Parser {
 	phase: Phase,
-	expression: Expression,
+	collection: Option<Vec<Token>>,
+	/// Opening brackets and braces within the current collection that are still unclosed.
+	nesting: Vec<Token>,
 	expressions: Vec<Expression>,
+	body: Vec<Token>,
 	statement: Statement,
+}
+
+impl Parser {
+	fn new() -> Self {
+		Self {
+			phase: Phase::Statement,
+			collection: None,
+			nesting: Vec::new(),
+			expressions: Vec::new(),
+			body: Vec::new(),
+			statement: Statement::Empty,
+		}
+	}
+
+	fn parse(tokens: &[Token]) -> Vec<Line> {
+		let mut parser = Self::new();
+		tokens
+			.iter()
+			.filter_map(|token| parser.take(token))
+			.collect()
+	}
+
+	/// Feeds the next token to the current line, returning the line once a break completes it.
+	fn take(&mut self, token: &Token) -> Option<Line> {
+		// When we reach a break, the line is complete.
+		if token.is(TokenType::Break) {
+			let line = Line {
+				statement: std::mem::replace(&mut self.statement, Statement::Empty),
+				expressions: std::mem::take(&mut self.expressions),
+				body: std::mem::take(&mut self.body),
+			};
+			self.phase = Phase::Statement;
+			self.collection = None;
+			self.nesting.clear();
+			return Some(line);
+		// First we need a statement.
+		} else if let Phase::Statement = self.phase {
+			if let Statement::Empty = self.statement {
+				if token.is(TokenType::Text) {
+					self.statement = Statement::Call(token.clone());
+					self.phase = Phase::Expressions;
+				} else if token.is_open() {
+					self.statement = Statement::Assign(Token::Empty);
+				}
+			// Then we need to fill an assignment statement.
+			} else if let Statement::Assign(assignment_token) = &self.statement {
+				if let Token::Empty = assignment_token {
+					if token.is(TokenType::Text) {
+						self.statement = Statement::Assign(token.clone());
+					}
+				} else if token.is_open() {
+					// A nested bracket after the name lists parameters, making it a function.
+					self.statement = Statement::Define(assignment_token.clone(), Vec::new());
+					self.phase = Phase::Params;
+				} else if token.is_close() {
+					self.phase = Phase::Expressions;
+				}
+			} else if let Statement::Define(_, _) = &self.statement {
+				if token.is_close() {
+					self.phase = Phase::Body;
+				}
+			}
+		// Function parameters are collected until their bracket closes.
+		} else if let Phase::Params = self.phase {
+			if let Statement::Define(_, params) = &mut self.statement {
+				if token.is(TokenType::Text) {
+					params.push(token.clone());
+				} else if token.is_close() {
+					self.phase = Phase::Statement;
+				}
+			}
+		// Function bodies are kept as tokens and evaluated when called.
+		} else if let Phase::Body = self.phase {
+			self.body.push(token.clone());
+		// Then we need an expression.
+		} else if let Phase::Expressions = self.phase {
+			if let Some(collection_tokens) = &mut self.collection {
+				// We continue building a collection until its own bracket closes.
+				if closes(&self.nesting, token) {
+					self
+						.expressions
+						.push(Expression::Collection(fragments(collection_tokens)));
+					self.collection = None;
+					self.nesting.clear();
+				} else {
+					nest(&mut self.nesting, token);
+					collection_tokens.push(token.clone());
+				}
+			} else if token.is(TokenType::Text) {
+				self.expressions.push(Expression::Lookup(token.clone()));
+			} else if token.is_open() {
+				self.collection = Some(Vec::new());
+			}
+		}
+		None
+	}
+}
+
+/// Tracks brackets that only count inside a splice, and the braces around them. A close drops
+/// whatever was left open since its match, so one unmatched bracket or brace can't hold the
+/// others open.
+fn nest(nesting: &mut Vec<Token>, token: &Token) {
+	let in_splice = nesting.last().is_some_and(|t| t.is_splice_open());
+	if token.is_splice_open() || (in_splice && token.is_open()) {
+		nesting.push(token.clone());
+	} else if token.is_splice_close() {
+		if let Some(i) = nesting.iter().rposition(|t| t.is_splice_open()) {
+			nesting.truncate(i);
+		}
+	} else if token.is_close() {
+		if let Some(i) = nesting.iter().rposition(|t| t.is_open()) {
+			nesting.truncate(i);
+		}
+	}
+}
+
+/// Whether `token` closes the collection around `nesting`. Only a bracket opened inside a
+/// splice can hold it open, so an unmatched brace does not swallow the rest of the line.
+fn closes(nesting: &[Token], token: &Token) -> bool {
+	token.is_close() && !nesting.iter().any(|t| t.is_open())
+}
+
+/// Splits the tokens of a collection into text and spliced expressions. A brace that is never
+/// closed is kept as text.
+fn fragments(tokens: &[Token]) -> Vec<Fragment> {
+	let mut fragments = Vec::new();
+	let mut i = 0;
+	while i < tokens.len() {
+		let end = if tokens[i].is_splice_open() {
+			closing(tokens, i)
+		} else {
+			tokens.len()
+		};
+		if end < tokens.len() {
+			fragments.push(Fragment::Splice(expressions(&tokens[i + 1..end])));
+			i = end + 1;
+		} else {
+			fragments.push(Fragment::Text(tokens[i].clone()));
+			i += 1;
+		}
+	}
+	fragments
+}
+
+/// Parses the inside of a splice the same way as the expressions following a statement.
+fn expressions(tokens: &[Token]) -> Vec<Expression> {
+	let mut expressions = Vec::new();
+	let mut i = 0;
+	while i < tokens.len() {
+		if tokens[i].is(TokenType::Text) {
+			expressions.push(Expression::Lookup(tokens[i].clone()));
+		} else if tokens[i].is_open() {
+			let end = closing(tokens, i);
+			expressions.push(Expression::Collection(fragments(&tokens[i + 1..end])));
+			i = end;
+		}
+		i += 1;
+	}
+	expressions
+}
+
+/// Finds the index of the token that closes the bracket or brace at `start`, or the end of the
+/// tokens if it is never closed. A brace whose splice leaves a bracket open doesn't count as
+/// closed either.
+fn closing(tokens: &[Token], start: usize) -> usize {
+	let mut nesting = vec![tokens[start].clone()];
+	for (i, token) in tokens.iter().enumerate().skip(start + 1) {
+		let open = nesting.len();
+		nest(&mut nesting, token);
+		if nesting.is_empty() {
+			return if tokens[start].is_splice_open() && open > 1 {
+				tokens.len()
+			} else {
+				i
+			};
+		}
+	}
+	tokens.len()
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+	params: Vec<String>,
+	body: Vec<Token>,
+}
+
+impl Function {
+	fn size(&self) -> usize {
+		self.params.iter().map(|p| p.len()).sum::<usize>()
+			+ self.body.iter().map(|t| t.content().len()).sum::<usize>()
+	}
+}
+
+#[derive(Debug)]
+struct Evaluator<'opts, W: Write> {
+	defs: HashMap<String, String>,
+	functions: HashMap<String, Function>,
+	steps: usize,
+	depth: usize,
+	output: usize,
+	memory: usize,
+	profiler: Option<Profiler>,
+	#[cfg(feature = "term")]
+	screen: Option<liib::screen::Screen>,
 	options: &'opts mut CliOptions<W>,
 }
 
@@ -191,99 +514,233 @@ impl<'opts, W: Write> Evaluator<'opts, W> {
 	fn new(options: &'opts mut CliOptions<W>) -> Self {
 		Self {
 			defs: HashMap::with_capacity(10),
-			phase: Phase::Statement,
-			expression: Expression::Empty,
-			expressions: Vec::new(),
-			statement: Statement::Empty,
+			functions: HashMap::new(),
+			steps: 0,
+			depth: 0,
+			output: 0,
+			memory: 0,
+			profiler: options.profile.as_ref().map(|_| Profiler::new()),
+			#[cfg(feature = "term")]
+			screen: None,
 			options,
 		}
 	}
 
-	fn eval(&mut self, tokens: Vec<Token>) {
+	fn eval(&mut self, tokens: &[Token]) -> Result<(), EvalError> {
+		let mut parser = Parser::new();
+		let mut line_number = 0;
 		for token in tokens.iter() {
-			// When we reach a break, evaluate it.
-			if token.is(TokenType::Break) {
-				println!("---------");
-				println!("Stmt: {:?}", self.statement);
-				println!("Exprs: {:?}", self.expressions);
-				println!("Defs: {:?}", self.defs);
-
-				if let Statement::Assign(t) = &self.statement {
-					self.defs.insert(t.content().to_string(), self.join());
-				} else if let Statement::Call(t) = &self.statement {
-					if t.content() == "print" {
-						writeln!(self.options.stdout, "{}", self.join()).unwrap();
+			self.step()?;
+
+			// When a line is complete, evaluate it.
+			if let Some(line) = parser.take(token) {
+				let statement = line.statement.name().to_string();
+				let start = Instant::now();
+				let result = self.execute(line);
+				// Lines within function bodies are attributed to the function instead.
+				if self.depth == 0 {
+					if let Some(profiler) = &mut self.profiler {
+						profiler.line(line_number, &statement, start.elapsed());
 					}
 				}
+				result?;
+				line_number += 1;
+			}
+		}
+		Ok(())
+	}
 
-				self.phase = Phase::Statement;
-				self.statement = Statement::Empty;
-				self.expression = Expression::Empty;
-				self.expressions.clear();
-			// First we need a statement.
-			} else if let Phase::Statement = self.phase {
-				if let Statement::Empty = self.statement {
-					if token.is(TokenType::Text) {
-						self.statement = Statement::Call(token.clone());
-						self.phase = Phase::Expressions;
-					} else if token.is_open() {
-						self.statement = Statement::Assign(Token::Empty);
-					}
-				// Then we need to fill an assignment statement.
-				} else if let Statement::Assign(assignment_token) = &self.statement {
-					if let Token::Empty = assignment_token {
-						if token.is(TokenType::Text) {
-							self.statement = Statement::Assign(token.clone());
-						}
-					} else if token.is_close() {
-						self.phase = Phase::Expressions;
+	fn report(&mut self) -> Result<(), EvalError> {
+		match (&self.profiler, &self.options.profile) {
+			(Some(profiler), Some(Profile::Report)) => self
+				.options
+				.stdout
+				.write_all(profiler.report().as_bytes())?,
+			(Some(profiler), Some(Profile::Json(path))) => fs::write(path, profiler.json())?,
+			_ => {}
+		}
+		Ok(())
+	}
+
+	fn execute(&mut self, line: Line) -> Result<(), EvalError> {
+		let Line {
+			statement,
+			expressions,
+			body,
+		} = line;
+		if let Statement::Assign(t) = &statement {
+			let value = self.join(&expressions)?;
+			self.define(t.content(), value)?;
+		} else if let Statement::Define(t, params) = &statement {
+			let function = Function {
+				params: params.iter().map(|p| p.content().to_string()).collect(),
+				body,
+			};
+			self.define_function(t.content(), function)?;
+		} else if let Statement::Call(t) = &statement {
+			if t.content() == "print" {
+				let line = format!("{}\n", self.join(&expressions)?);
+				self.write(&line)?;
+			} else {
+				let args = expressions
+					.iter()
+					.map(|expr| self.value(expr))
+					.collect::<Result<Vec<String>, EvalError>>()?;
+				#[cfg(feature = "term")]
+				{
+					if self.term(t.content(), &args)? {
+						return Ok(());
 					}
 				}
-			// Then we need an expression.
-			} else if let Phase::Expressions = self.phase {
-				if let Expression::Empty = self.expression {
-					if token.is(TokenType::Text) {
-						self.expressions.push(Expression::Lookup(token.clone()));
-						self.expression = Expression::Empty;
-					} else if token.is_open() {
-						self.expression = Expression::Collection(Vec::new());
-					}
-				// We continue building an expression.
-				} else if let Expression::Collection(expression_tokens) = &mut self.expression {
-					if token.is_close() {
-						self
-							.expressions
-							.push(Expression::Collection(expression_tokens.clone()));
-						self.expression = Expression::Empty;
-					} else {
-						expression_tokens.push(token.clone());
-					}
+				self.call(t.content(), args)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn call(&mut self, name: &str, args: Vec<String>) -> Result<(), EvalError> {
+		let function = match self.functions.get(name) {
+			Some(function) => function.clone(),
+			None => return Ok(()),
+		};
+
+		let start = Instant::now();
+		self.depth += 1;
+		let max = self
+			.options
+			.limits
+			.max_depth
+			.map_or(MAX_DEPTH, |max| max.min(MAX_DEPTH));
+		if self.depth > max {
+			self.depth -= 1;
+			return Err(EvalError::LimitExceeded(Limit::Depth(max)));
+		}
+		if let Some(profiler) = &mut self.profiler {
+			profiler.enter(name);
+		}
+
+		// Parameters shadow definitions for the duration of the call.
+		let mut shadowed = Vec::with_capacity(function.params.len());
+		let mut result = Ok(());
+		for (param, arg) in function.params.iter().zip(args) {
+			match self.define(param, arg) {
+				Ok(previous) => shadowed.push((param, previous)),
+				Err(e) => {
+					result = Err(e);
+					break;
 				}
 			}
 		}
+		if result.is_ok() {
+			let mut body = function.body.clone();
+			body.push(Token::Multi(TokenType::Break, "\n".to_string()));
+			// Deep recursion would overflow the thread's stack, so it grows as needed.
+			result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.eval(&body));
+		}
+		for (param, previous) in shadowed.into_iter().rev() {
+			self.restore(param, previous);
+		}
+
+		self.depth -= 1;
+		if let Some(profiler) = &mut self.profiler {
+			profiler.function(name, start.elapsed());
+		}
+		result
+	}
+
+	fn step(&mut self) -> Result<(), EvalError> {
+		self.steps += 1;
+		if let Some(max) = self.options.limits.max_steps {
+			if self.steps > max {
+				return Err(EvalError::LimitExceeded(Limit::Steps(max)));
+			}
+		}
+		Ok(())
 	}
 
-	fn join(&self) -> String {
-		self
-			.expressions
-			.iter()
-			.map(|expr| match expr {
-				Expression::Collection(tokens) => tokens
+	fn write(&mut self, s: &str) -> Result<(), EvalError> {
+		if let Some(max) = self.options.limits.max_output {
+			if self.output + s.len() > max {
+				return Err(EvalError::LimitExceeded(Limit::Output(max)));
+			}
+		}
+		self.options.stdout.write_all(s.as_bytes())?;
+		self.output += s.len();
+		Ok(())
+	}
+
+	/// Stores the value under the name, returning the value it replaced. Fails without storing
+	/// anything if the definitions would outgrow the memory limit.
+	fn define(&mut self, name: &str, value: String) -> Result<Option<String>, EvalError> {
+		let previous_size = self.defs.get(name).map_or(0, |v| name.len() + v.len());
+		self.reserve(name.len() + value.len(), previous_size)?;
+		Ok(self.defs.insert(name.to_string(), value))
+	}
+
+	fn undefine(&mut self, name: &str) {
+		if let Some(value) = self.defs.remove(name) {
+			self.memory -= name.len() + value.len();
+		}
+	}
+
+	/// Puts back a definition shadowed by a parameter. It was within the limits before, so this
+	/// doesn't check them.
+	fn restore(&mut self, name: &str, previous: Option<String>) {
+		self.undefine(name);
+		if let Some(value) = previous {
+			self.memory += name.len() + value.len();
+			self.defs.insert(name.to_string(), value);
+		}
+	}
+
+	fn define_function(&mut self, name: &str, function: Function) -> Result<(), EvalError> {
+		let previous_size = self
+			.functions
+			.get(name)
+			.map_or(0, |f| name.len() + f.size());
+		self.reserve(name.len() + function.size(), previous_size)?;
+		self.functions.insert(name.to_string(), function);
+		Ok(())
+	}
+
+	fn reserve(&mut self, size: usize, freed: usize) -> Result<(), EvalError> {
+		let memory = self.memory - freed + size;
+		if let Some(max) = self.options.limits.max_memory {
+			if memory > max {
+				return Err(EvalError::LimitExceeded(Limit::Memory(max)));
+			}
+		}
+		self.memory = memory;
+		Ok(())
+	}
+
+	fn join(&self, expressions: &[Expression]) -> Result<String, EvalError> {
+		Ok(
+			expressions
+				.iter()
+				.map(|expr| self.value(expr))
+				.collect::<Result<Vec<String>, EvalError>>()?
+				.join(""),
+		)
+	}
+
+	fn value(&self, expr: &Expression) -> Result<String, EvalError> {
+		match expr {
+			Expression::Collection(fragments) => Ok(
+				fragments
 					.iter()
-					.map(|token| token.content())
-					.collect::<Vec<&str>>()
+					.map(|fragment| match fragment {
+						Fragment::Text(token) => Ok(token.content().to_string()),
+						Fragment::Splice(expressions) => self.join(expressions),
+					})
+					.collect::<Result<Vec<String>, EvalError>>()?
 					.join(""),
-				Expression::Lookup(t) => {
-					let s: String = self
-						.defs
-						.get(t.content())
-						.unwrap_or_else(|| panic!("Invalid lookup: {}", t.content()))
-						.to_string();
-					s
-				}
-				Expression::Empty => "".to_string(),
-			})
-			.collect::<Vec<String>>()
-			.join("")
+			),
+			Expression::Lookup(t) => self
+				.defs
+				.get(t.content())
+				.cloned()
+				.ok_or_else(|| EvalError::InvalidLookup(t.content().to_string())),
+		}
 	}
 }
@@ -1,8 +1,9 @@
 use std::io::stdout;
+use std::process::exit;
 
-use clap::{App, Arg};
+use clap::{value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand};
 
-use laang::{eval, CliOptions};
+use laang::{compile, eval, CliOptions, Limits, Profile, Target};
 
 fn main() {
 	app()
@@ -10,6 +11,7 @@ fn main() {
 
 fn app() {
 	let matches = App::new("Vid Term")
+		.setting(AppSettings::SubcommandsNegateReqs)
 		.arg(
 			Arg::with_name("path")
 				.takes_value(true)
@@ -17,13 +19,107 @@ fn app() {
 				.index(1)
 				.help("Path to .laang"),
 		)
+		.arg(
+			Arg::with_name("max-steps")
+				.long("max-steps")
+				.takes_value(true)
+				.help("Maximum number of tokens to evaluate"),
+		)
+		.arg(
+			Arg::with_name("max-depth")
+				.long("max-depth")
+				.takes_value(true)
+				.help("Maximum depth of nested function calls"),
+		)
+		.arg(
+			Arg::with_name("max-output")
+				.long("max-output")
+				.takes_value(true)
+				.help("Maximum number of bytes to print"),
+		)
+		.arg(
+			Arg::with_name("max-memory")
+				.long("max-memory")
+				.takes_value(true)
+				.help("Maximum number of bytes held in definitions"),
+		)
+		.arg(
+			Arg::with_name("profile")
+				.long("profile")
+				.help("Print the time spent on each line and function"),
+		)
+		.arg(
+			Arg::with_name("profile-json")
+				.long("profile-json")
+				.takes_value(true)
+				.conflicts_with("profile")
+				.help("Write the time spent on each line and function as JSON to a file"),
+		)
+		.subcommand(
+			SubCommand::with_name("compile")
+				.about("Transpiles the script into a standalone program")
+				.arg(
+					Arg::with_name("path")
+						.takes_value(true)
+						.required(true)
+						.index(1)
+						.help("Path to .laang"),
+				)
+				.arg(
+					Arg::with_name("target")
+						.long("target")
+						.takes_value(true)
+						.required(true)
+						.possible_values(&["sh", "rust"])
+						.help("Language to write the program in"),
+				),
+		)
 		.get_matches();
 
+	if let Some(compile_matches) = matches.subcommand_matches("compile") {
+		let mut cli_opts = CliOptions {
+			path: compile_matches.value_of("path").unwrap_or("").to_string(),
+			stdout: stdout(),
+			limits: Limits::default(),
+			profile: None,
+		};
+		let target = value_t_or_exit!(compile_matches, "target", Target);
+		if let Err(e) = compile(&mut cli_opts, target) {
+			eprintln!("Error: {}", e);
+			exit(1);
+		}
+		return;
+	}
+
 	let path = matches.value_of("path").unwrap_or("");
 	let mut cli_opts = CliOptions {
 		path: path.to_string(),
 		stdout: stdout(),
+		limits: Limits {
+			max_steps: limit(&matches, "max-steps"),
+			max_depth: limit(&matches, "max-depth"),
+			max_output: limit(&matches, "max-output"),
+			max_memory: limit(&matches, "max-memory"),
+		},
+		profile: if let Some(path) = matches.value_of("profile-json") {
+			Some(Profile::Json(path.to_string()))
+		} else if matches.is_present("profile") {
+			Some(Profile::Report)
+		} else {
+			None
+		},
 	};
 
-	eval(&mut cli_opts)
+	if let Err(e) = eval(&mut cli_opts) {
+		eprintln!("Error: {}", e);
+		exit(1);
+	}
+}
+
+fn limit(matches: &ArgMatches, name: &str) -> Option<usize> {
+	if matches.is_present(name) {
+		Some(value_t_or_exit!(matches, name, usize))
+	} else {
+		None
+	}
 }
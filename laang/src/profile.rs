@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::time::Duration;
+
+/// Where to send the report collected with `--profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Profile {
+	/// A table printed to `stdout` after the script's output.
+	Report,
+	/// JSON written to the file at the path.
+	Json(String),
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Timing {
+	calls: usize,
+	total: Duration,
+}
+
+impl Timing {
+	fn record(&mut self, elapsed: Duration) {
+		self.calls += 1;
+		self.total += elapsed;
+	}
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Profiler {
+	lines: HashMap<usize, (String, Timing)>,
+	functions: HashMap<String, Timing>,
+	/// Calls to each function that haven't returned yet.
+	active: HashMap<String, usize>,
+}
+
+impl Profiler {
+	pub(crate) fn new() -> Self {
+		Self::default()
+	}
+
+	/// Records a top-level line. The time includes any function calls made on the line.
+	pub(crate) fn line(&mut self, number: usize, statement: &str, elapsed: Duration) {
+		self
+			.lines
+			.entry(number)
+			.or_insert_with(|| (statement.to_string(), Timing::default()))
+			.1
+			.record(elapsed);
+	}
+
+	/// Marks the start of a call to a user function, which [Profiler::function] records when it
+	/// returns.
+	pub(crate) fn enter(&mut self, name: &str) {
+		*self.active.entry(name.to_string()).or_default() += 1;
+	}
+
+	/// Records a call to a user function. Recursive calls are counted, but their time is only
+	/// added by the outermost one, whose time already includes theirs.
+	pub(crate) fn function(&mut self, name: &str, elapsed: Duration) {
+		let active = self.active.entry(name.to_string()).or_default();
+		*active = active.saturating_sub(1);
+		let elapsed = if *active == 0 {
+			elapsed
+		} else {
+			Duration::from_secs(0)
+		};
+		self
+			.functions
+			.entry(name.to_string())
+			.or_default()
+			.record(elapsed);
+	}
+
+	/// Lines by number and statement, slowest first.
+	fn sorted_lines(&self) -> Vec<(usize, &str, Timing)> {
+		let mut lines = self
+			.lines
+			.iter()
+			.map(|(&number, (statement, timing))| (number, statement.as_str(), *timing))
+			.collect::<Vec<_>>();
+		lines.sort_by(|a, b| b.2.total.cmp(&a.2.total).then(a.0.cmp(&b.0)));
+		lines
+	}
+
+	/// Functions by name, slowest first.
+	fn sorted_functions(&self) -> Vec<(&str, Timing)> {
+		let mut functions = self
+			.functions
+			.iter()
+			.map(|(name, timing)| (name.as_str(), *timing))
+			.collect::<Vec<_>>();
+		functions.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+		functions
+	}
+
+	pub(crate) fn report(&self) -> String {
+		let lines = self.sorted_lines();
+		let functions = self.sorted_functions();
+		let mut s = String::new();
+		writeln!(s, "---------").unwrap();
+		writeln!(s, "Profile:").unwrap();
+		writeln!(s, "Line\tCalls\tTotal\tStatement").unwrap();
+		for (number, statement, timing) in lines {
+			writeln!(
+				s,
+				"{}\t{}\t{:?}\t{}",
+				number, timing.calls, timing.total, statement
+			)
+			.unwrap();
+		}
+		writeln!(s, "Function\tCalls\tTotal").unwrap();
+		for (name, timing) in functions {
+			writeln!(s, "{}\t{}\t{:?}", name, timing.calls, timing.total).unwrap();
+		}
+		s
+	}
+
+	pub(crate) fn json(&self) -> String {
+		let lines = self.sorted_lines();
+		let functions = self.sorted_functions();
+		let lines = lines
+			.iter()
+			.map(|(number, statement, timing)| {
+				format!(
+					"{{\"line\":{},\"statement\":{},\"calls\":{},\"nanos\":{}}}",
+					number,
+					json_string(statement),
+					timing.calls,
+					timing.total.as_nanos()
+				)
+			})
+			.collect::<Vec<String>>();
+		let functions = functions
+			.iter()
+			.map(|(name, timing)| {
+				format!(
+					"{{\"name\":{},\"calls\":{},\"nanos\":{}}}",
+					json_string(name),
+					timing.calls,
+					timing.total.as_nanos()
+				)
+			})
+			.collect::<Vec<String>>();
+		format!(
+			"{{\"lines\":[{}],\"functions\":[{}]}}\n",
+			lines.join(","),
+			functions.join(",")
+		)
+	}
+}
+
+fn json_string(s: &str) -> String {
+	let mut escaped = String::with_capacity(s.len() + 2);
+	escaped.push('"');
+	for ch in s.chars() {
+		match ch {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			ch if ch.is_control() => write!(escaped, "\\u{:04x}", ch as u32).unwrap(),
+			ch => escaped.push(ch),
+		}
+	}
+	escaped.push('"');
+	escaped
+}
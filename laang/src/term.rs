@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+use liib::cell::Style;
+use liib::position::Position;
+use liib::screen::Screen;
+use liib::term::{dump_screen, get_size, make_room, read_key};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{EvalError, Evaluator};
+
+/// The names [Evaluator::term] runs, ahead of any function with the same name.
+pub(crate) const BUILTINS: &[&str] = &["clear", "draw", "flush", "key", "sleep"];
+
+impl<'opts, W: Write> Evaluator<'opts, W> {
+	/// Runs the terminal builtin with the name, returning false if there is no such builtin.
+	pub(crate) fn term(&mut self, name: &str, args: &[String]) -> Result<bool, EvalError> {
+		match name {
+			"clear" => self.screen().clear(),
+			"draw" => {
+				let col = number(args.first())?;
+				let row = number(args.get(1))?;
+				let text = args.get(2..).unwrap_or_default().join("");
+				let screen = self.screen();
+				let mut position = Position::new(col, row);
+				for g in UnicodeSegmentation::graphemes(text.as_str(), true) {
+					position.col += screen.write_grapheme(&position, g, Style::default());
+				}
+			}
+			"flush" => dump_screen(self.screen())?,
+			"key" => {
+				let key = match read_key()?.code {
+					KeyCode::Char(ch) => ch.to_string(),
+					KeyCode::Enter => "\n".to_string(),
+					KeyCode::Tab => "\t".to_string(),
+					code => format!("{:?}", code),
+				};
+				let name = args.first().cloned().unwrap_or_default();
+				self.define(&name, key)?;
+			}
+			"sleep" => sleep(Duration::from_millis(number(args.first())?.max(0) as u64)),
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
+	/// The screen is sized to the terminal and cleared the first time a builtin needs it.
+	fn screen(&mut self) -> &mut Screen {
+		self.screen.get_or_insert_with(|| {
+			make_room();
+			Screen::with_size(get_size())
+		})
+	}
+}
+
+fn number(arg: Option<&String>) -> Result<i32, EvalError> {
+	let arg = arg.map(|s| s.trim()).unwrap_or_default();
+	arg
+		.parse()
+		.map_err(|_| EvalError::InvalidArgument(format!("Expected a number, got '{}'", arg)))
+}
@@ -1,7 +1,31 @@
 use spectral::assert_that;
+use std::env;
+use std::fs;
 use std::path::Path;
+use std::process::{self, Command};
 
-use laang::{eval, CliOptions};
+use laang::{compile, eval, CliOptions, EvalError, Limit, Limits, Profile, Target, MAX_DEPTH};
+
+fn example_opts(name: &str, limits: Limits) -> CliOptions<Vec<u8>> {
+	CliOptions {
+		path: Path::new(env!("CARGO_MANIFEST_DIR"))
+			.join("examples")
+			.join(name)
+			.to_str()
+			.unwrap()
+			.to_string(),
+		stdout: Vec::new(),
+		limits,
+		profile: None,
+	}
+}
+
+fn limit_exceeded(name: &str, limits: Limits) -> Limit {
+	match eval(&mut example_opts(name, limits)) {
+		Err(EvalError::LimitExceeded(limit)) => limit,
+		other => panic!("Expected a limit to be exceeded, got {:?}", other),
+	}
+}
 
 #[test]
 fn test_vars() {
@@ -12,10 +36,235 @@ fn test_vars() {
 			.unwrap()
 			.to_string(),
 		stdout: Vec::new(),
+		limits: Limits::default(),
+		profile: None,
 	};
-	eval(&mut opts);
+	eval(&mut opts).unwrap();
 	let out: String = String::from_utf8(opts.stdout).unwrap();
 	assert_that!(out).is_equal_to("Hello world family 🗺\n".to_string());
 	// let out = opts.stdout;
 	// assert_that!(out).is_equal_to(Vec::from("Asdf"));
 }
+
+#[test]
+fn test_functions() {
+	let mut opts = example_opts("functions.laang", Limits::default());
+	eval(&mut opts).unwrap();
+	let out: String = String::from_utf8(opts.stdout).unwrap();
+	assert_that!(out).is_equal_to("Hello world\nfamily\n".to_string());
+}
+
+#[test]
+fn test_limits() {
+	let depth = Limits {
+		max_depth: Some(10),
+		..Limits::default()
+	};
+	assert_that!(limit_exceeded("recursion.laang", depth)).is_equal_to(Limit::Depth(10));
+
+	let steps = Limits {
+		max_steps: Some(1000),
+		..Limits::default()
+	};
+	assert_that!(limit_exceeded("recursion.laang", steps)).is_equal_to(Limit::Steps(1000));
+
+	let output = Limits {
+		max_output: Some(5),
+		..Limits::default()
+	};
+	assert_that!(limit_exceeded("vars.laang", output)).is_equal_to(Limit::Output(5));
+
+	let memory = Limits {
+		max_memory: Some(8),
+		..Limits::default()
+	};
+	assert_that!(limit_exceeded("vars.laang", memory)).is_equal_to(Limit::Memory(8));
+}
+
+#[test]
+fn test_deep_recursion() {
+	// Without a depth limit, or with one too deep for the stack, the built-in one applies.
+	for max_depth in [None, Some(usize::MAX)] {
+		let limits = Limits {
+			max_steps: Some(100_000_000),
+			max_depth,
+			..Limits::default()
+		};
+		assert_that!(limit_exceeded("recursion.laang", limits)).is_equal_to(Limit::Depth(MAX_DEPTH));
+	}
+}
+
+#[test]
+fn test_output_limit_writes_nothing_past_limit() {
+	let mut opts = example_opts(
+		"functions.laang",
+		Limits {
+			max_output: Some(15),
+			..Limits::default()
+		},
+	);
+	assert!(eval(&mut opts).is_err());
+	let out: String = String::from_utf8(opts.stdout).unwrap();
+	assert_that!(out).is_equal_to("Hello world\n".to_string());
+}
+
+fn interpreted(name: &str) -> String {
+	let mut opts = example_opts(name, Limits::default());
+	eval(&mut opts).unwrap();
+	String::from_utf8(opts.stdout).unwrap()
+}
+
+fn compiled(name: &str, target: Target) -> String {
+	let mut opts = example_opts(name, Limits::default());
+	compile(&mut opts, target).unwrap();
+	String::from_utf8(opts.stdout).unwrap()
+}
+
+fn run_compiled(name: &str, target: Target) -> String {
+	let dir = env::temp_dir().join(format!("laang_{}_{}", process::id(), name));
+	fs::create_dir_all(&dir).unwrap();
+	let output = match target {
+		Target::Sh => {
+			let script = dir.join("main.sh");
+			fs::write(&script, compiled(name, target)).unwrap();
+			Command::new("sh").arg(&script).output().unwrap()
+		}
+		Target::Rust => {
+			let source = dir.join("main.rs");
+			let binary = dir.join("main");
+			fs::write(&source, compiled(name, target)).unwrap();
+			let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+				.arg(&source)
+				.arg("-o")
+				.arg(&binary)
+				.status()
+				.unwrap();
+			assert!(status.success());
+			Command::new(&binary).output().unwrap()
+		}
+	};
+	fs::remove_dir_all(&dir).unwrap();
+	assert!(output.status.success());
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_compile_sh() {
+	for name in &[
+		"hello_world.laang",
+		"vars.laang",
+		"quotes.laang",
+		"interpolation.laang",
+		"braces.laang",
+	] {
+		assert_that!(run_compiled(name, Target::Sh)).is_equal_to(interpreted(name));
+	}
+}
+
+#[test]
+fn test_compile_rust() {
+	for name in &[
+		"hello_world.laang",
+		"vars.laang",
+		"quotes.laang",
+		"interpolation.laang",
+		"braces.laang",
+	] {
+		assert_that!(run_compiled(name, Target::Rust)).is_equal_to(interpreted(name));
+	}
+}
+
+#[test]
+fn test_compile_functions_unsupported() {
+	let mut opts = example_opts("functions.laang", Limits::default());
+	assert!(matches!(
+		compile(&mut opts, Target::Sh),
+		Err(EvalError::Unsupported(_))
+	));
+}
+
+#[cfg(feature = "term")]
+#[test]
+fn test_compile_term_unsupported() {
+	for target in &[Target::Sh, Target::Rust] {
+		let mut opts = example_opts("draw.laang", Limits::default());
+		assert!(matches!(
+			compile(&mut opts, *target),
+			Err(EvalError::Unsupported(_))
+		));
+	}
+}
+
+#[test]
+fn test_interpolation() {
+	assert_that!(interpreted("interpolation.laang"))
+		.is_equal_to("Hello world family 🗺\nHello world, family!\nHello world? } stays\n".to_string());
+}
+
+#[test]
+fn test_unmatched_brace() {
+	assert_that!(interpreted("braces.laang"))
+		.is_equal_to("a { b\nHello world { family\n{[}\n".to_string());
+}
+
+#[test]
+fn test_profile_report() {
+	let mut opts = example_opts("functions.laang", Limits::default());
+	opts.profile = Some(Profile::Report);
+	eval(&mut opts).unwrap();
+	let out: String = String::from_utf8(opts.stdout).unwrap();
+	let (output, report) = out.split_at(out.find("---------").unwrap());
+	assert_that!(output).is_equal_to("Hello world\nfamily\n");
+
+	let rows = report.lines().collect::<Vec<&str>>();
+	assert_that!(rows[2]).is_equal_to("Line\tCalls\tTotal\tStatement");
+	let mut statements = rows[3..6]
+		.iter()
+		.map(|row| {
+			let columns = row.split('\t').collect::<Vec<&str>>();
+			(columns[0], columns[1], columns[3])
+		})
+		.collect::<Vec<_>>();
+	statements.sort();
+	assert_that!(statements).is_equal_to(vec![("0", "1", "f"), ("1", "1", "f"), ("2", "1", "f")]);
+	assert_that!(rows[6]).is_equal_to("Function\tCalls\tTotal");
+	assert!(rows[7].starts_with("f\t2\t"));
+}
+
+#[test]
+fn test_profile_json() {
+	let path = env::temp_dir().join(format!("laang_{}_profile.json", process::id()));
+	let mut opts = example_opts("functions.laang", Limits::default());
+	opts.profile = Some(Profile::Json(path.to_str().unwrap().to_string()));
+	eval(&mut opts).unwrap();
+	let json = fs::read_to_string(&path).unwrap();
+	fs::remove_file(&path).unwrap();
+	assert!(json.starts_with("{\"lines\":[{\"line\":"));
+	assert!(json.contains("\"functions\":[{\"name\":\"f\",\"calls\":2,\"nanos\":"));
+}
+
+#[test]
+fn test_profile_recursion() {
+	let path = env::temp_dir().join(format!("laang_{}_recursion.json", process::id()));
+	let mut opts = example_opts(
+		"recursion.laang",
+		Limits {
+			max_depth: Some(50),
+			..Limits::default()
+		},
+	);
+	opts.profile = Some(Profile::Json(path.to_str().unwrap().to_string()));
+	assert!(eval(&mut opts).is_err());
+	let json = fs::read_to_string(&path).unwrap();
+	fs::remove_file(&path).unwrap();
+
+	let nanos = |after: &str| -> u128 {
+		let start = json.find(after).unwrap() + after.len();
+		let start = start + json[start..].find("\"nanos\":").unwrap() + "\"nanos\":".len();
+		let end = start + json[start..].find('}').unwrap();
+		json[start..end].parse().unwrap()
+	};
+	// Every level is counted, but the nested ones' time is only counted once, within the line.
+	assert!(json.contains("{\"name\":\"f\",\"calls\":50,"));
+	assert!(nanos("\"name\":\"f\"") <= nanos("\"line\":1,"));
+}
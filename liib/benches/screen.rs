@@ -0,0 +1,75 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use liib::position::Position;
+use liib::screen::Screen;
+
+const SIZE: (i32, i32) = (200, 60);
+
+/**
+ * Writes a full frame the way vid does, with the glyph depending on the frame so that roughly
+ * half the cells change each time.
+ */
+fn write_frame(screen: &mut Screen, frame: i32) {
+    let (cols, rows) = SIZE;
+    for row in 0..rows {
+        for col in 0..cols {
+            let ch = if (col + row + frame) % 4 < 2 {
+                'x'
+            } else {
+                '·'
+            };
+            screen.write(&Position::new(col, row), ch);
+        }
+    }
+}
+
+fn bench_screen(c: &mut Criterion) {
+    c.bench_function("full frame 200x60", |b| {
+        let mut screen = Screen::with_size(SIZE.into());
+        let mut frame = 0;
+        b.iter(|| {
+            frame += 1;
+            write_frame(&mut screen, frame);
+            black_box(screen.flush())
+        })
+    });
+
+    c.bench_function("unchanged frame 200x60", |b| {
+        let mut screen = Screen::with_size(SIZE.into());
+        write_frame(&mut screen, 0);
+        screen.flush();
+        b.iter(|| {
+            write_frame(&mut screen, 0);
+            black_box(screen.flush())
+        })
+    });
+
+    c.bench_function("sparse frame 200x60", |b| {
+        let mut screen = Screen::with_size(SIZE.into());
+        let mut frame = 0;
+        b.iter(|| {
+            frame += 1;
+            screen.write(&Position::new(frame % SIZE.0, frame % SIZE.1), 'O');
+            black_box(screen.flush())
+        })
+    });
+
+    c.bench_function("scrolling frame 200x60", |b| {
+        let mut screen = Screen::with_size(SIZE.into());
+        let mut frame = 0;
+        b.iter(|| {
+            frame += 1;
+            // Every row moves up by one, like a log tailing.
+            for row in 0..SIZE.1 {
+                let ch = (b'a' + ((row + frame) % 26) as u8) as char;
+                for col in 0..SIZE.0 {
+                    screen.write(&Position::new(col, row), ch);
+                }
+            }
+            black_box(screen.flush_runs(true))
+        })
+    });
+}
+
+criterion_group!(benches, bench_screen);
+criterion_main!(benches);
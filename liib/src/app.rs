@@ -0,0 +1,489 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::backend::Backend;
+use crate::cell::Style;
+use crate::screen::Screen;
+use crate::term::{draw_screen, Input};
+
+/**
+ * Whether an [App] goes on after handling something.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/**
+ * Something run by an [AppLoop]: it handles input as it comes, is updated at a fixed rate and
+ * rendered when there is time to.
+ */
+pub trait App<B: Backend> {
+    fn input(
+        &mut self,
+        input: Input,
+        screen: &mut Screen,
+        backend: &mut B,
+    ) -> crossterm::Result<Flow>;
+
+    /**
+     * Advances by one step of the loop's rate, or by the time since the last update when it has
+     * none.
+     */
+    fn update(&mut self, _step: Duration, _screen: &mut Screen) -> Flow {
+        Flow::Continue
+    }
+
+    /**
+     * Whether frames change without input, like an animation. When they don't, frames are only
+     * rendered after input.
+     */
+    fn animated(&self) -> bool {
+        true
+    }
+
+    /**
+     * Draws the frame on the screen, which is then drawn on the backend.
+     */
+    fn render(&mut self, _screen: &mut Screen) {}
+
+    /**
+     * Draws over the backend after the screen, for what isn't part of it, like status lines.
+     */
+    fn decorate(&mut self, _screen: &Screen, _backend: &mut B) -> crossterm::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * How often a [FrameTimer] has stepped and rendered.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FrameStats {
+    /// Frames rendered.
+    pub frames: u64,
+    /// Frames skipped to catch up after falling behind.
+    pub dropped: u64,
+    /// How long the last frame took, from its step to when it was rendered.
+    pub frame_time: Duration,
+    /// Frames rendered per second, smoothed.
+    pub fps: f64,
+}
+
+/**
+ * How many steps behind a [FrameTimer] may fall before it gives up catching up.
+ */
+const MAX_LAG_STEPS: u32 = 5;
+
+/**
+ * Schedules frames at a fixed rate. Each step is due a fixed time after the one before, however
+ * long the frames take, and when one is late the frames are dropped until it catches up.
+ */
+#[derive(Debug, Clone)]
+pub struct FrameTimer {
+    step: Option<Duration>,
+    next: Instant,
+    last: Instant,
+    elapsed: Duration,
+    rendered: Option<Instant>,
+    stats: FrameStats,
+}
+
+/**
+ * The time between steps at the rate, or None when the rate isn't a positive number, like the
+ * unknown frame rate of some videos.
+ */
+fn step_for(fps: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(1.0 / fps)
+        .ok()
+        .filter(|step| *step > Duration::from_secs(0))
+}
+
+impl FrameTimer {
+    /**
+     * Steps at the rate, or as soon as asked when it isn't a positive number, see
+     * [FrameTimer::unlimited].
+     */
+    pub fn new(fps: f64) -> Self {
+        let mut timer = Self::unlimited();
+        timer.step = step_for(fps);
+        timer
+    }
+
+    /**
+     * Steps as soon as asked, for when waiting is left to something else, like blocking input.
+     */
+    pub fn unlimited() -> Self {
+        let now = Instant::now();
+        Self {
+            step: None,
+            next: now,
+            last: now,
+            elapsed: Duration::from_secs(0),
+            rendered: None,
+            stats: FrameStats::default(),
+        }
+    }
+
+    /**
+     * The time from the last step to this one.
+     */
+    pub fn step(&self) -> Duration {
+        self.step.unwrap_or(self.elapsed)
+    }
+
+    /**
+     * How long until the next step is due.
+     */
+    pub fn timeout(&self) -> Duration {
+        self.next.saturating_duration_since(Instant::now())
+    }
+
+    /**
+     * Takes the next step, whether or not it is due yet. Returns false when the frame should be
+     * dropped, because the step after it is due already.
+     */
+    pub fn advance(&mut self) -> bool {
+        let now = Instant::now();
+        self.elapsed = now - self.last;
+        self.last = now;
+        let step = match self.step {
+            Some(step) => step,
+            None => return true,
+        };
+
+        self.next += step;
+        if now < self.next {
+            return true;
+        }
+        if now - self.next > step * MAX_LAG_STEPS {
+            // Too far behind, like after being suspended: start over from now.
+            self.next = now;
+        }
+        self.stats.dropped += 1;
+        false
+    }
+
+    /**
+     * Waits for the next step and takes it, see [FrameTimer::advance].
+     */
+    pub fn wait(&mut self) -> bool {
+        sleep(self.timeout());
+        self.advance()
+    }
+
+    /**
+     * Counts a frame as rendered.
+     */
+    pub fn rendered(&mut self) {
+        let now = Instant::now();
+        self.stats.frames += 1;
+        self.stats.frame_time = now - self.last;
+        if let Some(previous) = self.rendered {
+            let fps = 1.0 / (now - previous).as_secs_f64().max(1e-6);
+            self.stats.fps = if self.stats.fps == 0.0 {
+                fps
+            } else {
+                0.9 * self.stats.fps + 0.1 * fps
+            };
+        }
+        self.rendered = Some(now);
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /**
+     * Prints the stats in the top right corner, leaving the cursor where it was.
+     */
+    pub fn draw_overlay(&self, backend: &mut impl Backend) -> crossterm::Result<()> {
+        let text = format!(
+            " {:.0} fps {:.1} ms {} dropped ",
+            self.stats.fps,
+            self.stats.frame_time.as_secs_f64() * 1000.0,
+            self.stats.dropped
+        );
+        let col = backend.size().col - text.len() as i32;
+        backend.save_cursor()?;
+        backend.move_to(&(col.max(0), 0).into())?;
+        backend.set_style(&Style::default().reverse())?;
+        backend.print(&text)?;
+        backend.set_style(&Style::default())?;
+        backend.restore_cursor()?;
+        backend.flush()
+    }
+}
+
+/**
+ * How long an [AppLoop] without a rate waits for input at a time. It waits again when none
+ * came, so this only bounds each call to the input.
+ */
+const IDLE_WAIT: Duration = Duration::from_secs(60);
+
+/**
+ * Runs an [App] on a backend: input is handled as soon as it arrives, updates happen at the
+ * target rate and frames are rendered after them unless the loop has fallen behind, or nothing
+ * changed since the last one.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct AppLoop {
+    fps: Option<f64>,
+    overlay: bool,
+}
+
+impl AppLoop {
+    /**
+     * Updates at the rate, or like [AppLoop::unlimited] when it isn't a positive number.
+     */
+    pub fn new(fps: f64) -> Self {
+        Self {
+            fps: Some(fps).filter(|&fps| step_for(fps).is_some()),
+            overlay: false,
+        }
+    }
+
+    /**
+     * Updates and renders after each input, waiting for as long as it takes to come.
+     */
+    pub fn unlimited() -> Self {
+        Self {
+            fps: None,
+            overlay: false,
+        }
+    }
+
+    /**
+     * Shows the frame rate, frame time and dropped frames in the top right corner.
+     */
+    pub fn overlay(self, overlay: bool) -> Self {
+        Self { overlay, ..self }
+    }
+
+    /**
+     * Runs until the app quits. `next_input` waits at most the given time for input, see
+     * [crate::term::terminal_input]. Returns how the frames went.
+     */
+    pub fn run<B: Backend>(
+        &self,
+        app: &mut impl App<B>,
+        screen: &mut Screen,
+        backend: &mut B,
+        mut next_input: impl FnMut(Duration) -> crossterm::Result<Option<Input>>,
+    ) -> crossterm::Result<FrameStats> {
+        let mut timer = match self.fps {
+            Some(fps) => FrameTimer::new(fps),
+            None => FrameTimer::unlimited(),
+        };
+        self.render(&mut timer, app, screen, backend)?;
+
+        let mut changed = false;
+        loop {
+            // Input until the next step is due, and at least one wait for it. Without a rate,
+            // until there is some.
+            loop {
+                let timeout = match self.fps {
+                    Some(_) => timer.timeout(),
+                    None => IDLE_WAIT,
+                };
+                if let Some(input) = next_input(timeout)? {
+                    if app.input(input, screen, backend)? == Flow::Quit {
+                        return Ok(*timer.stats());
+                    }
+                    changed = true;
+                    if self.fps.is_none() {
+                        break;
+                    }
+                }
+                if self.fps.is_some() && timer.timeout() == Duration::from_secs(0) {
+                    break;
+                }
+            }
+
+            let render = timer.advance();
+            if app.update(timer.step(), screen) == Flow::Quit {
+                return Ok(*timer.stats());
+            }
+            if render && (changed || app.animated()) {
+                self.render(&mut timer, app, screen, backend)?;
+                changed = false;
+            }
+        }
+    }
+
+    fn render<B: Backend>(
+        &self,
+        timer: &mut FrameTimer,
+        app: &mut impl App<B>,
+        screen: &mut Screen,
+        backend: &mut B,
+    ) -> crossterm::Result<()> {
+        app.render(screen);
+        draw_screen(screen, backend)?;
+        app.decorate(screen, backend)?;
+        timer.rendered();
+        if self.overlay {
+            timer.draw_overlay(backend)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_frame_timer() {
+    let mut timer = FrameTimer::new(100.0);
+    assert_eq!(timer.step(), Duration::from_millis(10));
+    assert!(timer.timeout() <= Duration::from_millis(10));
+    assert!(timer.wait());
+
+    // Far behind, so it drops the frame and starts over.
+    sleep(Duration::from_millis(100));
+    assert!(!timer.advance());
+    assert_eq!(timer.stats().dropped, 1);
+    assert!(timer.advance());
+    assert!(timer.timeout() > Duration::from_secs(0));
+
+    timer.rendered();
+    timer.rendered();
+    assert_eq!(timer.stats().frames, 2);
+    assert!(timer.stats().fps > 0.0);
+
+    let mut timer = FrameTimer::unlimited();
+    sleep(Duration::from_millis(2));
+    assert!(timer.advance());
+    assert!(timer.step() >= Duration::from_millis(2));
+    assert_eq!(timer.timeout(), Duration::from_secs(0));
+
+    // Rates that can't be stepped at, like a video's unknown one, don't wait.
+    for &fps in &[0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+        let mut timer = FrameTimer::new(fps);
+        assert!(timer.advance());
+        assert_eq!(timer.timeout(), Duration::from_secs(0), "{}", fps);
+    }
+}
+
+#[test]
+fn test_app_loop() {
+    use crate::backend::MemoryBackend;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[derive(Default)]
+    struct Counter {
+        inputs: usize,
+        updates: usize,
+    }
+
+    impl App<MemoryBackend> for Counter {
+        fn input(
+            &mut self,
+            input: Input,
+            _screen: &mut Screen,
+            _backend: &mut MemoryBackend,
+        ) -> crossterm::Result<Flow> {
+            self.inputs += 1;
+            Ok(match input {
+                Input::Key(_) => Flow::Quit,
+                _ => Flow::Continue,
+            })
+        }
+
+        fn update(&mut self, _step: Duration, _screen: &mut Screen) -> Flow {
+            self.updates += 1;
+            Flow::Continue
+        }
+
+        fn render(&mut self, screen: &mut Screen) {
+            screen.write(&(0, 1).into(), (b'0' + self.updates as u8) as char);
+        }
+    }
+
+    // Without a rate, waits that end without input are waited again.
+    let mut inputs = vec![
+        None,
+        Some(Input::Resize((40, 2).into())),
+        None,
+        Some(Input::Resize((40, 2).into())),
+        Some(Input::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))),
+    ]
+    .into_iter();
+    let mut app = Counter::default();
+    let mut screen = Screen::with_size((40, 2).into());
+    let mut backend = MemoryBackend::with_size((40, 2).into());
+    let stats = AppLoop::unlimited()
+        .overlay(true)
+        .run(&mut app, &mut screen, &mut backend, |_| {
+            Ok(inputs.next().unwrap())
+        })
+        .unwrap();
+
+    assert_eq!((app.inputs, app.updates), (3, 2));
+    assert_eq!(stats.frames, 3);
+    assert_eq!(stats.dropped, 0);
+    let text = crate::save::to_text(backend.screen());
+    assert!(text.ends_with(" 0 dropped\n2\n"), "{:?}", text);
+    assert!(text.contains(" fps "));
+}
+
+#[test]
+fn test_app_loop_idle() {
+    use crate::backend::MemoryBackend;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[derive(Default)]
+    struct Still {
+        updates: usize,
+        renders: usize,
+    }
+
+    impl App<MemoryBackend> for Still {
+        fn input(
+            &mut self,
+            input: Input,
+            _screen: &mut Screen,
+            _backend: &mut MemoryBackend,
+        ) -> crossterm::Result<Flow> {
+            Ok(match input {
+                Input::Key(_) => Flow::Quit,
+                _ => Flow::Continue,
+            })
+        }
+
+        fn update(&mut self, _step: Duration, _screen: &mut Screen) -> Flow {
+            self.updates += 1;
+            Flow::Continue
+        }
+
+        fn animated(&self) -> bool {
+            false
+        }
+
+        fn render(&mut self, _screen: &mut Screen) {
+            self.renders += 1;
+        }
+    }
+
+    let mut inputs = vec![
+        None,
+        None,
+        Some(Input::Resize((4, 2).into())),
+        None,
+        None,
+        Some(Input::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))),
+    ]
+    .into_iter();
+    let mut app = Still::default();
+    let mut screen = Screen::with_size((4, 2).into());
+    let mut backend = MemoryBackend::with_size((4, 2).into());
+    let stats = AppLoop::new(100.0)
+        .run(&mut app, &mut screen, &mut backend, |timeout| {
+            sleep(timeout);
+            Ok(inputs.next().unwrap())
+        })
+        .unwrap();
+
+    // Updated at every step, but only rendered at the start and after the resize.
+    assert!(app.updates >= 3, "{}", app.updates);
+    assert_eq!(app.renders, 2);
+    assert_eq!(stats.frames, 2);
+}
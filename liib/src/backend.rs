@@ -0,0 +1,316 @@
+use std::io::Write;
+
+use crossterm::{
+    cursor::{position as crossterm_position, MoveTo, RestorePosition, SavePosition},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{size as crossterm_size, Clear, ClearType, ScrollDown, ScrollUp},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::cell::{Cell, Style};
+use crate::color::{queue_style, ColorSupport};
+use crate::position::Position;
+use crate::screen::{Resize, Screen};
+use crate::term::{terminal_out, TerminalOut};
+
+/**
+ * Where [crate::term] sends its output. Commands may be buffered until [Backend#flush].
+ */
+pub trait Backend {
+    fn size(&self) -> Position;
+    fn cursor(&mut self) -> crossterm::Result<Position>;
+    fn move_to(&mut self, position: &Position) -> crossterm::Result<()>;
+    fn save_cursor(&mut self) -> crossterm::Result<()>;
+    fn restore_cursor(&mut self) -> crossterm::Result<()>;
+    /**
+     * Changes the style of what is printed next, from the one last set.
+     */
+    fn set_style(&mut self, style: &Style) -> crossterm::Result<()>;
+    /**
+     * Goes back to the default style, whatever was set before.
+     */
+    fn reset_style(&mut self) -> crossterm::Result<()>;
+    /**
+     * Prints the text at the cursor, moving the cursor past it.
+     */
+    fn print(&mut self, text: &str) -> crossterm::Result<()>;
+    fn clear(&mut self) -> crossterm::Result<()>;
+    fn clear_line(&mut self) -> crossterm::Result<()>;
+    /**
+     * Moves everything shown up by the number of rows, or down if negative.
+     */
+    fn scroll(&mut self, rows: i32) -> crossterm::Result<()>;
+    fn bell(&mut self) -> crossterm::Result<()>;
+    fn flush(&mut self) -> crossterm::Result<()>;
+}
+
+/**
+ * Writes crossterm commands to stdout, or any other writer. Colours are downsampled to what the
+ * terminal supports, detected when the backend is made.
+ */
+pub struct CrosstermBackend<W: Write> {
+    out: W,
+    style: Style,
+    size: Option<Position>,
+    colors: ColorSupport,
+}
+
+impl CrosstermBackend<TerminalOut> {
+    /**
+     * Writes to standard output through [terminal_out], so it is recorded too.
+     */
+    pub fn stdout() -> Self {
+        Self::new(terminal_out())
+    }
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            style: Style::default(),
+            size: None,
+            colors: ColorSupport::detect(),
+        }
+    }
+
+    /**
+     * Reports the size instead of asking the terminal, for when the writer isn't one.
+     */
+    pub fn with_size(out: W, size: Position) -> Self {
+        Self {
+            size: Some(size),
+            ..Self::new(out)
+        }
+    }
+
+    /**
+     * Uses these colours instead of the detected ones.
+     */
+    pub fn colors(mut self, colors: ColorSupport) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn size(&self) -> Position {
+        self.size
+            .unwrap_or_else(|| crossterm_size().unwrap_or((0, 0)).into())
+    }
+
+    fn cursor(&mut self) -> crossterm::Result<Position> {
+        Ok(crossterm_position()?.into())
+    }
+
+    fn move_to(&mut self, position: &Position) -> crossterm::Result<()> {
+        let visible = position.saturating_visible();
+        queue!(self.out, MoveTo(visible.0, visible.1))
+    }
+
+    fn save_cursor(&mut self) -> crossterm::Result<()> {
+        queue!(self.out, SavePosition)
+    }
+
+    fn restore_cursor(&mut self) -> crossterm::Result<()> {
+        queue!(self.out, RestorePosition)
+    }
+
+    fn set_style(&mut self, style: &Style) -> crossterm::Result<()> {
+        let style = self.colors.downsample_style(style);
+        queue_style(&mut self.out, &self.style, &style)?;
+        self.style = style;
+        Ok(())
+    }
+
+    fn reset_style(&mut self) -> crossterm::Result<()> {
+        self.style = Style::default();
+        queue!(self.out, SetAttribute(Attribute::Reset))
+    }
+
+    fn print(&mut self, text: &str) -> crossterm::Result<()> {
+        queue!(self.out, Print(text))
+    }
+
+    fn clear(&mut self) -> crossterm::Result<()> {
+        queue!(self.out, Clear(ClearType::All))
+    }
+
+    fn clear_line(&mut self) -> crossterm::Result<()> {
+        queue!(self.out, Clear(ClearType::CurrentLine))
+    }
+
+    fn scroll(&mut self, rows: i32) -> crossterm::Result<()> {
+        if rows > 0 {
+            queue!(self.out, ScrollUp(rows as u16))
+        } else {
+            queue!(self.out, ScrollDown(rows.unsigned_abs() as u16))
+        }
+    }
+
+    fn bell(&mut self) -> crossterm::Result<()> {
+        queue!(self.out, Print(7 as char))
+    }
+
+    fn flush(&mut self) -> crossterm::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/**
+ * A pretend terminal that interprets the commands into a [Screen], so what would have been
+ * drawn can be asserted on. Text that reaches the right edge is cut off rather than wrapped.
+ */
+#[derive(Debug)]
+pub struct MemoryBackend {
+    screen: Screen,
+    cursor: Position,
+    saved: Position,
+    style: Style,
+    bells: usize,
+}
+
+impl MemoryBackend {
+    pub fn with_size(col_row: Position) -> Self {
+        Self {
+            screen: Screen::with_size(col_row),
+            cursor: Position::new(0, 0),
+            saved: Position::new(0, 0),
+            style: Style::default(),
+            bells: 0,
+        }
+    }
+
+    /**
+     * What the terminal shows, up to the last flush.
+     */
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    pub fn bells(&self) -> usize {
+        self.bells
+    }
+
+    /**
+     * Resizes like a terminal window, cropping what no longer fits.
+     */
+    pub fn resize(&mut self, size: Position) {
+        self.screen.resize(size, Resize::Crop);
+        self.cursor = self.screen.clamp(&self.cursor);
+        self.saved = self.screen.clamp(&self.saved);
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn size(&self) -> Position {
+        Position::new(self.screen.cols, self.screen.rows)
+    }
+
+    fn cursor(&mut self) -> crossterm::Result<Position> {
+        Ok(self.cursor)
+    }
+
+    fn move_to(&mut self, position: &Position) -> crossterm::Result<()> {
+        // Like the terminal, clamped to the screen.
+        self.cursor = self.screen.clamp(position);
+        Ok(())
+    }
+
+    fn save_cursor(&mut self) -> crossterm::Result<()> {
+        self.saved = self.cursor;
+        Ok(())
+    }
+
+    fn restore_cursor(&mut self) -> crossterm::Result<()> {
+        self.cursor = self.saved;
+        Ok(())
+    }
+
+    fn set_style(&mut self, style: &Style) -> crossterm::Result<()> {
+        self.style = *style;
+        Ok(())
+    }
+
+    fn reset_style(&mut self) -> crossterm::Result<()> {
+        self.style = Style::default();
+        Ok(())
+    }
+
+    fn print(&mut self, text: &str) -> crossterm::Result<()> {
+        for grapheme in text.graphemes(true) {
+            if self.screen.contains(&self.cursor) {
+                let width = self
+                    .screen
+                    .write_grapheme(&self.cursor, grapheme, self.style);
+                self.cursor.col += width;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> crossterm::Result<()> {
+        self.screen.clear();
+        Ok(())
+    }
+
+    fn clear_line(&mut self) -> crossterm::Result<()> {
+        for col in 0..self.screen.cols {
+            self.screen
+                .write_cell(&(col, self.cursor.row).into(), Cell::default());
+        }
+        Ok(())
+    }
+
+    fn scroll(&mut self, rows: i32) -> crossterm::Result<()> {
+        self.screen.scroll(rows);
+        Ok(())
+    }
+
+    fn bell(&mut self) -> crossterm::Result<()> {
+        self.bells += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> crossterm::Result<()> {
+        self.screen.flush();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_memory_backend() {
+    use crossterm::style::Color;
+
+    let red = Style::default().fg(Color::Red);
+    let mut backend = MemoryBackend::with_size((4, 2).into());
+    backend.move_to(&(1, 0).into()).unwrap();
+    backend.save_cursor().unwrap();
+    backend.set_style(&red).unwrap();
+    backend.print("abcdef").unwrap();
+    backend.restore_cursor().unwrap();
+    backend.move_to(&(9, 1).into()).unwrap();
+    backend.reset_style().unwrap();
+    backend.print("z").unwrap();
+    backend.bell().unwrap();
+
+    // Nothing shows before a flush.
+    assert_eq!(backend.screen().read(&(1, 0).into()), ' ');
+    backend.flush().unwrap();
+    assert_eq!(crate::save::to_text(backend.screen()), " abc\n   z\n");
+    assert_eq!(
+        backend.screen().read_cell(&(1, 0).into()),
+        Cell::styled('a', red)
+    );
+    assert_eq!(backend.cursor().unwrap(), (4, 1).into());
+    assert_eq!(backend.bells(), 1);
+
+    backend.clear_line().unwrap();
+    backend.flush().unwrap();
+    assert_eq!(crate::save::to_text(backend.screen()), " abc\n\n");
+}
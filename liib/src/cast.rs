@@ -0,0 +1,799 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::backend::{Backend, MemoryBackend};
+use crate::cell::Style;
+use crate::position::Position;
+use crate::save::apply_sgr;
+use crate::screen::Screen;
+
+/**
+ * A recorded terminal session, as read from an asciicast v2 file: a header line with the size of
+ * the terminal, then one line per event with the seconds since the start.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub size: Position,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Since the recording started.
+    pub time: Duration,
+    pub data: Data,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Data {
+    /// Text written to the terminal, escapes included.
+    Output(String),
+    /// Text typed into the terminal.
+    Input(String),
+    /// The new size of the terminal.
+    Resize(Position),
+    /// A point of interest, with its label.
+    Marker(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for CastError {}
+
+impl Cast {
+    /**
+     * Reads an asciicast v2 recording. Events of kinds other than output, input, resize and
+     * marker are skipped, like the format asks.
+     */
+    pub fn parse(text: &str) -> Result<Cast, CastError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let error = |line: usize, message: String| CastError {
+            line: line + 1,
+            message,
+        };
+
+        let (i, header) = lines
+            .next()
+            .ok_or_else(|| error(0, "missing header".to_string()))?;
+        let header = parse_json(header).map_err(|e| error(i, e))?;
+        if header.get("version").and_then(Json::as_number) != Some(2.0) {
+            return Err(error(i, "only version 2 is supported".to_string()));
+        }
+        let dimension = |name: &str| {
+            header
+                .get(name)
+                .and_then(Json::as_number)
+                .map(|n| n as i32)
+                .ok_or_else(|| error(i, format!("missing {}", name)))
+        };
+        let size = Position::new(dimension("width")?, dimension("height")?);
+
+        let mut events = Vec::new();
+        for (i, line) in lines {
+            let event = parse_json(line).map_err(|e| error(i, e))?;
+            let (time, code, data) = match &event {
+                Json::Array(values) => match values.as_slice() {
+                    [Json::Number(time), Json::String(code), Json::String(data)] => {
+                        let time = Duration::try_from_secs_f64(*time)
+                            .map_err(|_| error(i, format!("bad time {}", time)))?;
+                        (time, code, data)
+                    }
+                    _ => return Err(error(i, "expected [time, code, data]".to_string())),
+                },
+                _ => return Err(error(i, "expected an array".to_string())),
+            };
+            let data = match code.as_str() {
+                "o" => Data::Output(data.clone()),
+                "i" => Data::Input(data.clone()),
+                "m" => Data::Marker(data.clone()),
+                "r" => Data::Resize(
+                    parse_size(data).ok_or_else(|| error(i, format!("bad size {:?}", data)))?,
+                ),
+                _ => continue,
+            };
+            events.push(Event { time, data });
+        }
+
+        Ok(Cast { size, events })
+    }
+
+    pub fn load_file(path: impl AsRef<Path>) -> io::Result<Cast> {
+        Cast::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /**
+     * How long the recording lasts, up to its last event.
+     */
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map_or(Duration::ZERO, |event| event.time)
+    }
+}
+
+/**
+ * Parses `COLSxROWS`.
+ */
+fn parse_size(text: &str) -> Option<Position> {
+    let (cols, rows) = text.split_once('x')?;
+    Some(Position::new(cols.parse().ok()?, rows.parse().ok()?))
+}
+
+/**
+ * Writes what is sent to the terminal as an asciicast v2 recording, timed from when it was
+ * made. Use it as the writer of a [crate::backend::CrosstermBackend], or see
+ * [crate::term::record] to record everything `liib::term` draws.
+ */
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+    partial: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /**
+     * Starts the recording by writing the header for a terminal of the size.
+     */
+    pub fn new(mut out: W, size: Position) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        writeln!(
+            out,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}}}",
+            size.col, size.row, timestamp
+        )?;
+        Ok(Self {
+            out,
+            start: Instant::now(),
+            partial: Vec::new(),
+        })
+    }
+
+    /**
+     * Records that the terminal changed size.
+     */
+    pub fn resize(&mut self, size: Position) -> io::Result<()> {
+        self.event("r", &format!("{}x{}", size.col, size.row))
+    }
+
+    /**
+     * Records a point of interest that players can jump to.
+     */
+    pub fn marker(&mut self, label: &str) -> io::Result<()> {
+        self.event("m", label)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "[{:.6}, {}, {}]",
+            self.start.elapsed().as_secs_f64(),
+            json_string(code),
+            json_string(data)
+        )
+    }
+}
+
+/**
+ * Records the bytes as output. A character split between two writes waits for the rest.
+ */
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.partial) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            // Anything else invalid won't get better, it is replaced.
+            _ => self.partial.len(),
+        };
+        let bytes: Vec<u8> = self.partial.drain(..complete).collect();
+        let text = String::from_utf8_lossy(&bytes);
+        if !text.is_empty() {
+            self.event("o", &text)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/**
+ * Replays a [Cast] at some multiple of the speed it was recorded at, into the terminal or a
+ * headless [Screen].
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Player {
+    speed: f64,
+    until: Option<Duration>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Player {
+    /**
+     * Plays at the speed, 2.0 being twice as fast. An infinite speed doesn't wait at all. Panics
+     * unless the speed is positive.
+     */
+    pub fn new(speed: f64) -> Self {
+        assert!(speed > 0.0, "speed must be positive, not {}", speed);
+        Self { speed, until: None }
+    }
+
+    /**
+     * Plays everything straight away, for when only the end result matters.
+     */
+    pub fn instant() -> Self {
+        Self::new(f64::INFINITY)
+    }
+
+    /**
+     * Stops before the first event after the time into the recording.
+     */
+    pub fn until(mut self, time: Duration) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /**
+     * Writes the output to a terminal as it was recorded. It can't be resized, so the terminal
+     * should be the recorded size.
+     */
+    pub fn play(&self, cast: &Cast, out: &mut impl Write) -> crossterm::Result<()> {
+        self.run(cast, |data| {
+            if let Data::Output(text) = data {
+                out.write_all(text.as_bytes())?;
+                out.flush()?;
+            }
+            Ok(())
+        })
+    }
+
+    /**
+     * Interprets the output into the backend, see [Emulator].
+     */
+    pub fn play_backend(&self, cast: &Cast, backend: &mut impl Backend) -> crossterm::Result<()> {
+        let mut emulator = Emulator::new();
+        self.run(cast, |data| {
+            if let Data::Output(text) = data {
+                emulator.feed(backend, text)?;
+                backend.flush()?;
+            }
+            Ok(())
+        })
+    }
+
+    /**
+     * What the terminal showed at the end, interpreting the output like [Player::play_backend]
+     * and following resizes.
+     */
+    pub fn play_screen(&self, cast: &Cast) -> crossterm::Result<Screen> {
+        let mut backend = MemoryBackend::with_size(cast.size);
+        let mut emulator = Emulator::new();
+        self.run(cast, |data| {
+            match data {
+                Data::Output(text) => emulator.feed(&mut backend, text)?,
+                Data::Resize(size) => backend.resize(*size),
+                _ => {}
+            }
+            backend.flush()
+        })?;
+        Ok(backend.screen().clone())
+    }
+
+    fn run(
+        &self,
+        cast: &Cast,
+        mut each: impl FnMut(&Data) -> crossterm::Result<()>,
+    ) -> crossterm::Result<()> {
+        let start = Instant::now();
+        for event in &cast.events {
+            if self.until.is_some_and(|until| event.time > until) {
+                break;
+            }
+            if self.speed.is_finite() {
+                let due = event.time.div_f64(self.speed);
+                let elapsed = start.elapsed();
+                if due > elapsed {
+                    sleep(due - elapsed);
+                }
+            }
+            each(&event.data)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Interprets text written to a terminal into [Backend] calls: printing, moving the cursor,
+ * styles, clearing and scrolling. Other escapes, like those that switch to the alternate screen
+ * or hide the cursor, are skipped. An escape split between two pieces of text waits for the
+ * rest.
+ */
+#[derive(Debug, Default)]
+pub struct Emulator {
+    pending: String,
+    style: Style,
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, backend: &mut impl Backend, text: &str) -> crossterm::Result<()> {
+        self.pending.push_str(text);
+        let text = std::mem::take(&mut self.pending);
+        let mut rest = text.as_str();
+
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '\x1b' => {
+                    let sequence = &rest[1..];
+                    if let Some(csi) = sequence.strip_prefix('[') {
+                        // Parameters run until the final byte, between '@' and '~'.
+                        match csi.find(|c: char| ('@'..='~').contains(&c)) {
+                            Some(end) => {
+                                let final_byte = csi[end..].chars().next().unwrap();
+                                self.csi(backend, &csi[..end], final_byte)?;
+                                rest = &csi[end + final_byte.len_utf8()..];
+                            }
+                            None => break,
+                        }
+                    } else {
+                        match sequence.chars().next() {
+                            Some('7') => backend.save_cursor()?,
+                            Some('8') => backend.restore_cursor()?,
+                            Some(_) => {}
+                            None => break,
+                        }
+                        let skipped = sequence.chars().next().map_or(0, char::len_utf8);
+                        rest = &sequence[skipped..];
+                    }
+                    continue;
+                }
+                '\r' => {
+                    let cursor = backend.cursor()?;
+                    backend.move_to(&(0, cursor.row).into())?;
+                }
+                '\n' => {
+                    let cursor = backend.cursor()?;
+                    if cursor.row + 1 >= backend.size().row {
+                        backend.scroll(1)?;
+                    } else {
+                        backend.move_to(&(cursor + Position::DOWN))?;
+                    }
+                }
+                '\x07' => backend.bell()?,
+                '\x08' => {
+                    let cursor = backend.cursor()?;
+                    backend.move_to(&(cursor + Position::LEFT))?;
+                }
+                '\t' => {
+                    let cursor = backend.cursor()?;
+                    backend.move_to(&((cursor.col / 8 + 1) * 8, cursor.row).into())?;
+                }
+                c if c.is_control() => {}
+                _ => {
+                    let end = rest.find(char::is_control).unwrap_or(rest.len());
+                    backend.print(&rest[..end])?;
+                    rest = &rest[end..];
+                    continue;
+                }
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+
+        self.pending = rest.to_string();
+        Ok(())
+    }
+
+    /**
+     * Handles a Control Sequence Introducer escape, `ESC [ parameters final`.
+     */
+    fn csi(
+        &mut self,
+        backend: &mut impl Backend,
+        parameters: &str,
+        final_byte: char,
+    ) -> crossterm::Result<()> {
+        // Private modes, like `?25l` to hide the cursor.
+        if parameters.starts_with('?') {
+            return Ok(());
+        }
+        let values: Vec<Option<i32>> = parameters.split(';').map(|v| v.parse().ok()).collect();
+        let n = |i: usize, default: i32| values.get(i).copied().flatten().unwrap_or(default);
+        let cursor = backend.cursor()?;
+
+        match final_byte {
+            'H' | 'f' => backend.move_to(&(n(1, 1) - 1, n(0, 1) - 1).into())?,
+            'A' => backend.move_to(&(cursor + Position::UP * n(0, 1)))?,
+            'B' => backend.move_to(&(cursor + Position::DOWN * n(0, 1)))?,
+            'C' => backend.move_to(&(cursor + Position::RIGHT * n(0, 1)))?,
+            'D' => backend.move_to(&(cursor + Position::LEFT * n(0, 1)))?,
+            'E' => backend.move_to(&(0, cursor.row + n(0, 1)).into())?,
+            'F' => backend.move_to(&(0, cursor.row - n(0, 1)).into())?,
+            'G' => backend.move_to(&(n(0, 1) - 1, cursor.row).into())?,
+            'd' => backend.move_to(&(cursor.col, n(0, 1) - 1).into())?,
+            'J' if n(0, 0) >= 2 => backend.clear()?,
+            'K' if n(0, 0) == 2 => backend.clear_line()?,
+            'S' => backend.scroll(n(0, 1))?,
+            'T' => backend.scroll(-n(0, 1))?,
+            's' => backend.save_cursor()?,
+            'u' => backend.restore_cursor()?,
+            'm' => {
+                apply_sgr(&mut self.style, parameters);
+                backend.set_style(&self.style)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/**
+ * The text as a JSON string, quoted and escaped.
+ */
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/**
+ * Just enough JSON for the lines of a recording.
+ */
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut chars = text.trim().chars().peekable();
+    let value = parse_value(&mut chars)?;
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected {:?} after the value", c)),
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Chars, expected: char) -> Result<(), String> {
+    skip_whitespace(chars);
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("expected {:?}, found {:?}", expected, c)),
+        None => Err(format!("expected {:?}, found the end", expected)),
+    }
+}
+
+fn parse_value(chars: &mut Chars) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(values)),
+                    _ => return Err("expected ',' or ']'".to_string()),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                expect(chars, ':')?;
+                fields.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err("expected ',' or '}'".to_string()),
+                }
+            }
+        }
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            number
+                .parse()
+                .map(Json::Number)
+                .map_err(|_| format!("bad number {:?}", number))
+        }
+        Some(c) if c.is_ascii_alphabetic() => {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                word.push(c);
+                chars.next();
+            }
+            match word.as_str() {
+                "null" => Ok(Json::Null),
+                "true" => Ok(Json::Bool(true)),
+                "false" => Ok(Json::Bool(false)),
+                _ => Err(format!("unexpected {:?}", word)),
+            }
+        }
+        Some(c) => Err(format!("unexpected {:?}", c)),
+        None => Err("unexpected end".to_string()),
+    }
+}
+
+fn parse_string(chars: &mut Chars) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('b') => text.push('\x08'),
+                Some('f') => text.push('\x0c'),
+                Some('u') => {
+                    let mut code = parse_hex(chars)?;
+                    // Characters outside the basic plane come as a surrogate pair.
+                    if (0xd800..0xdc00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("unpaired surrogate".to_string());
+                        }
+                        let low = parse_hex(chars)?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00));
+                    }
+                    text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(c) => text.push(c),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => text.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+fn parse_hex(chars: &mut Chars) -> Result<u32, String> {
+    let digits: String = chars.take(4).collect();
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("bad escape \\u{}", digits))
+}
+
+#[test]
+fn test_parse() {
+    let text = concat!(
+        "{\"version\": 2, \"width\": 80, \"height\": 24, \"env\": {\"TERM\": \"xterm\"}}\n",
+        "[0.5, \"o\", \"hi\\r\\n\\u001b[1m\\\"\\ud83d\\ude00\\\"\"]\n",
+        "\n",
+        "[1, \"i\", \"q\"]\n",
+        "[1.25, \"r\", \"100x30\"]\n",
+        "[1.5, \"x\", \"unknown\"]\n",
+        "[2.0, \"m\", \"end\"]\n",
+    );
+    let cast = Cast::parse(text).unwrap();
+    assert_eq!(cast.size, (80, 24).into());
+    assert_eq!(
+        cast.events,
+        vec![
+            Event {
+                time: Duration::from_millis(500),
+                data: Data::Output("hi\r\n\x1b[1m\"😀\"".to_string()),
+            },
+            Event {
+                time: Duration::from_secs(1),
+                data: Data::Input("q".to_string()),
+            },
+            Event {
+                time: Duration::from_millis(1250),
+                data: Data::Resize((100, 30).into()),
+            },
+            Event {
+                time: Duration::from_secs(2),
+                data: Data::Marker("end".to_string()),
+            },
+        ]
+    );
+    assert_eq!(cast.duration(), Duration::from_secs(2));
+
+    let error = |text: &str| Cast::parse(text).unwrap_err();
+    assert_eq!(error("").line, 1);
+    assert_eq!(
+        error("{\"version\": 1}").message,
+        "only version 2 is supported"
+    );
+    assert_eq!(
+        error("{\"version\": 2, \"width\": 3}").message,
+        "missing height"
+    );
+    let bad_event = error("{\"version\": 2, \"width\": 3, \"height\": 1}\n[1, \"o\"]");
+    assert_eq!(
+        bad_event,
+        CastError {
+            line: 2,
+            message: "expected [time, code, data]".to_string()
+        }
+    );
+    let header = "{\"version\": 2, \"width\": 3, \"height\": 1}\n";
+    assert_eq!(
+        error(&format!("{}[1e20, \"o\", \"x\"]", header)).message,
+        "bad time 100000000000000000000"
+    );
+    assert_eq!(
+        error(&format!("{}[-1, \"o\", \"x\"]", header)).message,
+        "bad time -1"
+    );
+}
+
+#[test]
+fn test_recorder() {
+    let mut recorder = Recorder::new(Vec::new(), (10, 2).into()).unwrap();
+    recorder.write_all(b"a\"\n").unwrap();
+    // The character is split between the writes.
+    let smile = "😀".as_bytes();
+    recorder.write_all(&smile[..2]).unwrap();
+    recorder.write_all(&smile[2..]).unwrap();
+    recorder.resize((4, 3).into()).unwrap();
+    recorder.marker("done").unwrap();
+
+    let cast = Cast::parse(&String::from_utf8(recorder.into_inner()).unwrap()).unwrap();
+    assert_eq!(cast.size, (10, 2).into());
+    let data: Vec<Data> = cast.events.into_iter().map(|event| event.data).collect();
+    assert_eq!(
+        data,
+        vec![
+            Data::Output("a\"\n".to_string()),
+            Data::Output("😀".to_string()),
+            Data::Resize((4, 3).into()),
+            Data::Marker("done".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_play() {
+    use crate::backend::CrosstermBackend;
+    use crate::color::ColorSupport;
+    use crate::term::draw_screen;
+    use crossterm::style::Color;
+
+    // What a screen draws is recorded and played back headless.
+    let size = Position::new(12, 4);
+    let mut screen = Screen::with_size(size);
+    crate::draw::write_str(
+        &mut screen,
+        &(1, 1).into(),
+        "recorded 漢",
+        Style::default().fg(Color::Red).bold(),
+        crate::draw::Wrap::Clip,
+    );
+    screen.write(&(11, 3).into(), '!');
+    let recorder = Recorder::new(Vec::new(), size).unwrap();
+    let mut backend = CrosstermBackend::with_size(recorder, size).colors(ColorSupport::TrueColor);
+    draw_screen(&mut screen, &mut backend).unwrap();
+    let text = String::from_utf8(backend.into_inner().into_inner()).unwrap();
+
+    let cast = Cast::parse(&text).unwrap();
+    let played = Player::instant().play_screen(&cast).unwrap();
+    assert!(crate::snapshot::diff(&played, &screen).is_none());
+
+    // Straight to the terminal, it writes the same output.
+    let mut out: Vec<u8> = Vec::new();
+    Player::instant().play(&cast, &mut out).unwrap();
+    let output: String = cast
+        .events
+        .iter()
+        .filter_map(|event| match &event.data {
+            Data::Output(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(String::from_utf8(out).unwrap(), output);
+
+    // Timed, scrolled and resized.
+    let cast = Cast::parse(concat!(
+        "{\"version\": 2, \"width\": 4, \"height\": 2}\n",
+        "[0.0, \"o\", \"ab\\r\\ncd\\n\\u001b[1\"]\n",
+        "[0.5, \"o\", \";3Hx\"]\n",
+        "[1.0, \"r\", \"3x2\"]\n",
+        "[2.0, \"o\", \"\\u001b[2J\"]\n",
+    ))
+    .unwrap();
+    let start = Instant::now();
+    let played = Player::new(50.0)
+        .until(Duration::from_secs(1))
+        .play_screen(&cast)
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!((played.cols, played.rows), (3, 2));
+    crate::snapshot::assert_screen(&played, "cdx\n");
+}
@@ -0,0 +1,212 @@
+use core::fmt;
+
+use crossterm::style::Color;
+use unicode_width::UnicodeWidthChar;
+
+pub const BLANK: char = ' ';
+
+/**
+ * What [Grapheme::ch] returns for the second column of a wide grapheme.
+ */
+pub const CONTINUATION: char = '\0';
+
+/**
+ * The user-perceived character drawn in a cell. Graphemes made of several code points, like
+ * emoji with modifiers, are kept whole. A grapheme that is two columns wide is followed by a
+ * [Grapheme::Continuation] in the next cell.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Grapheme {
+    Char(char),
+    Cluster(Box<str>),
+    Continuation,
+}
+
+impl Grapheme {
+    pub fn new(s: &str) -> Self {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => Grapheme::Char(BLANK),
+            (Some(ch), None) => Grapheme::Char(ch),
+            _ => Grapheme::Cluster(s.into()),
+        }
+    }
+
+    /**
+     * The first code point, which is enough to tell most graphemes apart.
+     */
+    pub fn ch(&self) -> char {
+        match self {
+            Grapheme::Char(ch) => *ch,
+            Grapheme::Cluster(s) => s.chars().next().unwrap_or(BLANK),
+            Grapheme::Continuation => CONTINUATION,
+        }
+    }
+
+    /**
+     * Number of columns the grapheme occupies: 2 for wide CJK and emoji, 0 for a continuation
+     * and 1 for everything else, including zero-width code points written on their own.
+     */
+    pub fn width(&self) -> usize {
+        match self {
+            Grapheme::Char(ch) => ch.width().unwrap_or(1).clamp(1, 2),
+            Grapheme::Cluster(s) => {
+                // An emoji presentation selector widens the base character.
+                if s.contains('\u{FE0F}') {
+                    2
+                } else {
+                    self.ch().width().unwrap_or(1).clamp(1, 2)
+                }
+            }
+            Grapheme::Continuation => 0,
+        }
+    }
+}
+
+impl fmt::Display for Grapheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grapheme::Char(ch) => write!(f, "{}", ch),
+            Grapheme::Cluster(s) => write!(f, "{}", s),
+            Grapheme::Continuation => Ok(()),
+        }
+    }
+}
+
+/**
+ * How a cell is drawn. [Color::Reset] leaves the terminal's own colour in place.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            bold: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+}
+
+impl Style {
+    pub fn fg(self, fg: Color) -> Self {
+        Self { fg, ..self }
+    }
+
+    pub fn bg(self, bg: Color) -> Self {
+        Self { bg, ..self }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    pub fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        Self {
+            reverse: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub grapheme: Grapheme,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new(BLANK)
+    }
+}
+
+impl Cell {
+    pub fn new(ch: char) -> Self {
+        Self::styled(ch, Style::default())
+    }
+
+    pub fn styled(ch: char, style: Style) -> Self {
+        Self {
+            grapheme: Grapheme::Char(ch),
+            style,
+        }
+    }
+
+    pub fn with_grapheme(grapheme: &str, style: Style) -> Self {
+        Self {
+            grapheme: Grapheme::new(grapheme),
+            style,
+        }
+    }
+
+    pub fn ch(&self) -> char {
+        self.grapheme.ch()
+    }
+
+    pub fn width(&self) -> usize {
+        self.grapheme.width()
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.grapheme == Grapheme::Continuation
+    }
+
+    /**
+     * Whether this is the default, unstyled blank. When compositing layers, whatever is below
+     * shows through it. A blank with any style set is opaque.
+     */
+    pub fn is_transparent(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl From<char> for Cell {
+    fn from(ch: char) -> Self {
+        Self::new(ch)
+    }
+}
+
+#[test]
+fn test_style() {
+    let style = Style::default().fg(Color::Red).bold();
+    assert_eq!(style.fg, Color::Red);
+    assert_eq!(style.bg, Color::Reset);
+    assert!(style.bold && !style.underline && !style.reverse);
+
+    assert_eq!(Cell::from('x'), Cell::styled('x', Style::default()));
+    assert_ne!(Cell::new('x'), Cell::styled('x', style));
+}
+
+#[test]
+fn test_grapheme() {
+    assert_eq!(Grapheme::new("a"), Grapheme::Char('a'));
+    assert_eq!(Grapheme::new(""), Grapheme::Char(BLANK));
+    assert_eq!(Grapheme::new("e\u{301}").to_string(), "e\u{301}");
+
+    assert_eq!(Grapheme::new("a").width(), 1);
+    assert_eq!(Grapheme::new("✖").width(), 1);
+    assert_eq!(Grapheme::new("漢").width(), 2);
+    assert_eq!(Grapheme::new("👪").width(), 2);
+    assert_eq!(Grapheme::new("e\u{301}").width(), 1);
+    assert_eq!(Grapheme::new("❤\u{FE0F}").width(), 2);
+    assert_eq!(Grapheme::new("👩\u{200D}👩\u{200D}👧").width(), 2);
+    assert_eq!(Grapheme::Continuation.width(), 0);
+    assert_eq!(Grapheme::Continuation.ch(), CONTINUATION);
+}
@@ -0,0 +1,318 @@
+use std::env;
+use std::io::Write;
+use std::str::FromStr;
+
+use crossterm::{
+    queue,
+    style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor},
+};
+
+use crate::cell::Style;
+
+/**
+ * The environment variable that overrides the detected [ColorSupport], set to `truecolor`,
+ * `256`, `16` or `mono`.
+ */
+pub const COLOR_VAR: &str = "LIIB_COLOR";
+
+/**
+ * How many colours the terminal can show, from fewest to most.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorSupport {
+    /// No colours at all, only the terminal's own.
+    Mono,
+    /// The 16 named colours.
+    Ansi16,
+    /// The 256 colour palette.
+    Ansi256,
+    /// Any RGB colour.
+    TrueColor,
+}
+
+impl ColorSupport {
+    /**
+     * Guesses what the terminal supports from the environment, unless [COLOR_VAR] says.
+     */
+    pub fn detect() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /**
+     * Like [ColorSupport::detect], reading variables with the function. `NO_COLOR` wins over
+     * `COLORTERM`, which wins over `TERM`.
+     */
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(support) = var(COLOR_VAR).and_then(|v| v.parse().ok()) {
+            return support;
+        }
+        if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorSupport::Mono;
+        }
+        if let Some("truecolor" | "24bit") = var("COLORTERM").as_deref() {
+            return ColorSupport::TrueColor;
+        }
+        match var("TERM").as_deref() {
+            Some("dumb") => ColorSupport::Mono,
+            Some(term) if term.ends_with("-direct") => ColorSupport::TrueColor,
+            Some(term) if term.contains("256color") => ColorSupport::Ansi256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /**
+     * The closest colour the terminal can show.
+     */
+    pub fn downsample(self, color: Color) -> Color {
+        match (self, color) {
+            (_, Color::Reset) | (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::Mono, _) => Color::Reset,
+            (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => {
+                Color::AnsiValue(nearest_256((r, g, b)))
+            }
+            (ColorSupport::Ansi256, _) => color,
+            (ColorSupport::Ansi16, Color::Rgb { r, g, b }) => nearest_16((r, g, b)),
+            (ColorSupport::Ansi16, Color::AnsiValue(n)) => nearest_16(palette_rgb(n)),
+            (ColorSupport::Ansi16, _) => color,
+        }
+    }
+
+    /**
+     * The style with both colours downsampled.
+     */
+    pub fn downsample_style(self, style: &Style) -> Style {
+        style
+            .fg(self.downsample(style.fg))
+            .bg(self.downsample(style.bg))
+    }
+}
+
+impl FromStr for ColorSupport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(ColorSupport::TrueColor),
+            "256" => Ok(ColorSupport::Ansi256),
+            "16" => Ok(ColorSupport::Ansi16),
+            "mono" | "none" => Ok(ColorSupport::Mono),
+            _ => Err(format!("unknown colour support {:?}", s)),
+        }
+    }
+}
+
+/**
+ * The usual xterm values of the 16 named colours, in palette order.
+ */
+const ANSI_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each component in the 6x6x6 cube of the 256 colour palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn palette_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI_16[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (
+                CUBE_LEVELS[(n / 36) as usize],
+                CUBE_LEVELS[(n / 6 % 6) as usize],
+                CUBE_LEVELS[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (n - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/**
+ * The palette index of the closest colour in the cube or the grey ramp, leaving out the 16
+ * named colours that terminals are free to change.
+ */
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40,
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, palette_rgb(grey)) < distance(rgb, palette_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    let n = (0..16)
+        .min_by_key(|&n| distance(rgb, ANSI_16[n as usize]))
+        .unwrap_or(0);
+    Color::parse_ansi(&format!("5;{}", n)).unwrap_or(Color::AnsiValue(n))
+}
+
+/**
+ * Queues only the commands needed to change the terminal from one style to another.
+ */
+pub fn queue_style(out: &mut impl Write, from: &Style, to: &Style) -> crossterm::Result<()> {
+    if from.fg != to.fg {
+        queue!(out, SetForegroundColor(to.fg))?;
+    }
+    if from.bg != to.bg {
+        queue!(out, SetBackgroundColor(to.bg))?;
+    }
+    if from.bold != to.bold {
+        let attribute = if to.bold {
+            Attribute::Bold
+        } else {
+            Attribute::NormalIntensity
+        };
+        queue!(out, SetAttribute(attribute))?;
+    }
+    if from.underline != to.underline {
+        let attribute = if to.underline {
+            Attribute::Underlined
+        } else {
+            Attribute::NoUnderline
+        };
+        queue!(out, SetAttribute(attribute))?;
+    }
+    if from.reverse != to.reverse {
+        let attribute = if to.reverse {
+            Attribute::Reverse
+        } else {
+            Attribute::NoReverse
+        };
+        queue!(out, SetAttribute(attribute))?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_queue_style() {
+    let plain = Style::default();
+    let red = plain.fg(Color::Red).bold();
+
+    let mut out: Vec<u8> = Vec::new();
+    queue_style(&mut out, &plain, &plain).unwrap();
+    assert!(out.is_empty());
+
+    queue_style(&mut out, &plain, &red).unwrap();
+    let mut expected: Vec<u8> = Vec::new();
+    queue!(
+        expected,
+        SetForegroundColor(Color::Red),
+        SetAttribute(Attribute::Bold)
+    )
+    .unwrap();
+    assert_eq!(out, expected);
+
+    out.clear();
+    queue_style(&mut out, &red, &red.fg(Color::Reset).underline()).unwrap();
+    expected.clear();
+    queue!(
+        expected,
+        SetForegroundColor(Color::Reset),
+        SetAttribute(Attribute::Underlined)
+    )
+    .unwrap();
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_color_support() {
+    let vars = |pairs: &'static [(&'static str, &'static str)]| {
+        move |name: &str| {
+            pairs
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        }
+    };
+    use ColorSupport::*;
+    assert_eq!(ColorSupport::from_vars(vars(&[])), Ansi16);
+    assert_eq!(
+        ColorSupport::from_vars(vars(&[("TERM", "xterm-256color")])),
+        Ansi256
+    );
+    assert_eq!(
+        ColorSupport::from_vars(vars(&[
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor")
+        ])),
+        TrueColor
+    );
+    assert_eq!(ColorSupport::from_vars(vars(&[("TERM", "dumb")])), Mono);
+    assert_eq!(
+        ColorSupport::from_vars(vars(&[("COLORTERM", "24bit"), ("NO_COLOR", "1")])),
+        Mono
+    );
+    // An empty NO_COLOR doesn't count.
+    assert_eq!(ColorSupport::from_vars(vars(&[("NO_COLOR", "")])), Ansi16);
+    assert_eq!(
+        ColorSupport::from_vars(vars(&[("NO_COLOR", "1"), (COLOR_VAR, "256")])),
+        Ansi256
+    );
+    // An override that isn't understood is ignored.
+    assert_eq!(
+        ColorSupport::from_vars(vars(&[("TERM", "dumb"), (COLOR_VAR, "lots")])),
+        Mono
+    );
+
+    let orange = Color::Rgb {
+        r: 255,
+        g: 135,
+        b: 0,
+    };
+    assert_eq!(TrueColor.downsample(orange), orange);
+    assert_eq!(Ansi256.downsample(orange), Color::AnsiValue(208));
+    assert_eq!(Ansi16.downsample(orange), Color::DarkYellow);
+    assert_eq!(Mono.downsample(orange), Color::Reset);
+
+    let grey = Color::Rgb {
+        r: 100,
+        g: 101,
+        b: 99,
+    };
+    assert_eq!(Ansi256.downsample(grey), Color::AnsiValue(241));
+    assert_eq!(Ansi16.downsample(Color::AnsiValue(196)), Color::Red);
+    assert_eq!(Ansi16.downsample(Color::DarkBlue), Color::DarkBlue);
+    assert_eq!(
+        Ansi256.downsample(Color::AnsiValue(196)),
+        Color::AnsiValue(196)
+    );
+
+    let style = Style::default().fg(orange).bg(Color::Reset).bold();
+    assert_eq!(
+        Ansi16.downsample_style(&style),
+        Style::default().fg(Color::DarkYellow).bold()
+    );
+}
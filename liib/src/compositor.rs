@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use crate::position::{Position, Rect};
+use crate::screen::{Resize, Screen};
+
+/**
+ * A named sub-screen drawn at an offset into the composited screen. Layers with a higher `z`
+ * are drawn over lower ones, and layers with the same `z` in the order they were added.
+ */
+#[derive(Debug)]
+pub struct Layer {
+    pub name: String,
+    pub offset: Position,
+    pub z: i32,
+    pub visible: bool,
+    pub screen: Screen,
+}
+
+/**
+ * Stacks layers into one [Screen] so they can be flushed together, instead of each being dumped
+ * on its own and overwriting the others. Transparent cells (see [Cell#is_transparent]) let the
+ * layers below show through.
+ */
+#[derive(Debug, Default)]
+pub struct Compositor {
+    layers: Vec<Layer>,
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Adds an empty, fully transparent layer, replacing any layer with the same name. Returns
+     * its screen to draw on.
+     */
+    pub fn add(&mut self, name: &str, offset: Position, size: Position, z: i32) -> &mut Screen {
+        self.remove(name);
+        self.layers.push(Layer {
+            name: name.to_string(),
+            offset,
+            z,
+            visible: true,
+            screen: Screen::with_size(size),
+        });
+        let last = self.layers.len() - 1;
+        &mut self.layers[last].screen
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Layer> {
+        let i = self.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.layers.remove(i))
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /**
+     * The screen of the named layer, for drawing.
+     */
+    pub fn screen(&mut self, name: &str) -> Option<&mut Screen> {
+        self.layer_mut(name).map(|layer| &mut layer.screen)
+    }
+
+    /**
+     * Moves the layers named after the panes to them and resizes them to fit, as solved by a
+     * [crate::layout::Layout]. Content that no longer fits is cropped. Other layers are left as
+     * they are.
+     */
+    pub fn arrange(&mut self, panes: &HashMap<String, Rect>) {
+        for layer in self.layers.iter_mut() {
+            if let Some(pane) = panes.get(&layer.name) {
+                layer.offset = pane.origin;
+                if layer.screen.bounds().size != pane.size {
+                    layer.screen.resize(pane.size, Resize::Crop);
+                }
+            }
+        }
+    }
+
+    /**
+     * Draws every visible layer into the target, bottom to top. Cells no layer covers are
+     * blanked, so the target ends up showing exactly the layers; only what changed since the
+     * last composite is flushed from it afterwards.
+     */
+    pub fn compose(&mut self, target: &mut Screen) {
+        self.layers.sort_by_key(|layer| layer.z);
+
+        target.clear();
+        for layer in self.layers.iter_mut().filter(|layer| layer.visible) {
+            layer.screen.flush();
+            // Only the part of the layer that lands on the target.
+            let mut area = layer.screen.bounds();
+            area.origin += layer.offset;
+            for position in area.intersect(&target.bounds()).positions() {
+                let cell = layer.screen.read_cell(&(position - layer.offset));
+                // Wide cells bring their own continuation.
+                if cell.is_transparent() || cell.is_continuation() {
+                    continue;
+                }
+                target.write_cell(&position, cell);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compose() {
+    use crate::cell::{Cell, Style};
+
+    let mut target = Screen::with_size((4, 2).into());
+    let mut compositor = Compositor::new();
+
+    let back = compositor.add("back", (0, 0).into(), (4, 2).into(), 0);
+    crate::scrite!(back, (0, 0, 'a'), (1, 0, 'b'), (2, 0, 'c'), (3, 1, 'd'));
+
+    // Added later but drawn first.
+    let front = compositor.add("front", (1, 0).into(), (4, 4).into(), 1);
+    front.write(&(0, 0).into(), 'x');
+    // An opaque blank still covers what is below.
+    let opaque = Style::default().reverse();
+    front.write_styled(&(1, 0).into(), ' ', opaque);
+    // Falls outside the target.
+    front.write(&(3, 1).into(), 'y');
+
+    compositor.compose(&mut target);
+    target.flush();
+    let row: String = (0..4).map(|c| target.read(&(c, 0).into())).collect();
+    assert_eq!(row, "ax  ");
+    assert_eq!(target.read_cell(&(2, 0).into()), Cell::styled(' ', opaque));
+    assert_eq!(target.read(&(3, 1).into()), 'd');
+
+    // Reordering layers only flushes what changed.
+    compositor.layer_mut("front").unwrap().z = -1;
+    compositor.compose(&mut target);
+    let out = target.flush();
+    assert_eq!(out.len(), 2);
+    assert_eq!(target.read(&(1, 0).into()), 'b');
+
+    compositor.layer_mut("back").unwrap().visible = false;
+    compositor.compose(&mut target);
+    target.flush();
+    assert_eq!(target.read(&(0, 0).into()), ' ');
+    assert_eq!(target.read(&(3, 1).into()), ' ');
+
+    assert!(compositor.remove("back").is_some());
+    assert!(compositor.screen("back").is_none());
+    assert!(compositor.screen("front").is_some());
+}
+
+#[test]
+fn test_arrange() {
+    use crate::layout::{Constraint, Layout};
+
+    let layout = Layout::columns()
+        .pane("wave", Constraint::Fixed(2))
+        .pane("video", Constraint::Fill(1));
+    let mut compositor = Compositor::new();
+    compositor.add("wave", (0, 0).into(), (0, 0).into(), 1);
+    compositor.add("video", (0, 0).into(), (9, 9).into(), 0);
+    compositor
+        .screen("video")
+        .unwrap()
+        .write(&(0, 0).into(), 'v');
+
+    compositor.arrange(&layout.solve(&Rect::from((5, 1))));
+    let video = compositor.layer("video").unwrap();
+    assert_eq!(video.offset, (2, 0).into());
+    assert_eq!(video.screen.bounds().size, (3, 1).into());
+
+    let mut target = Screen::with_size((5, 1).into());
+    compositor.compose(&mut target);
+    target.flush();
+    assert_eq!(crate::save::to_text(&target), "  v\n");
+}
@@ -0,0 +1,309 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::cell::{Cell, Style};
+use crate::position::Position;
+use crate::screen::Screen;
+
+/**
+ * What [write_str] does with text that reaches the right edge of the screen.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Wrap {
+    /// Drop the rest of the line, up to the next newline.
+    Clip,
+    /// Continue on the next row, at the column the text started in.
+    Wrap,
+}
+
+/**
+ * The characters a rectangle's border is drawn with.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Border {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+}
+
+impl Border {
+    pub const ASCII: Border = Border::new('-', '|', ['+', '+', '+', '+']);
+    pub const LIGHT: Border = Border::new('─', '│', ['┌', '┐', '┘', '└']);
+    pub const HEAVY: Border = Border::new('━', '┃', ['┏', '┓', '┛', '┗']);
+    pub const DOUBLE: Border = Border::new('═', '║', ['╔', '╗', '╝', '╚']);
+    pub const ROUNDED: Border = Border::new('─', '│', ['╭', '╮', '╯', '╰']);
+
+    /**
+     * Corners go clockwise from the top left.
+     */
+    pub const fn new(horizontal: char, vertical: char, corners: [char; 4]) -> Self {
+        Self {
+            horizontal,
+            vertical,
+            top_left: corners[0],
+            top_right: corners[1],
+            bottom_right: corners[2],
+            bottom_left: corners[3],
+        }
+    }
+}
+
+/**
+ * Writes the text one grapheme at a time starting at the position, and returns the position
+ * just after the last one. Newlines go back to the starting column on the next row. Anything
+ * that falls off the screen is not drawn.
+ */
+pub fn write_str(
+    screen: &mut Screen,
+    start: &Position,
+    text: &str,
+    style: Style,
+    wrap: Wrap,
+) -> Position {
+    let mut cursor = *start;
+    for grapheme in text.graphemes(true) {
+        if grapheme == "\n" || grapheme == "\r\n" {
+            cursor = Position::new(start.col, cursor.row + 1);
+            continue;
+        }
+
+        let cell = Cell::with_grapheme(grapheme, style);
+        let width = cell.width() as i32;
+        if cursor.col + width > screen.cols {
+            match wrap {
+                Wrap::Clip => {
+                    cursor.col += width;
+                    continue;
+                }
+                // A line that can't fit a single grapheme is clipped instead.
+                Wrap::Wrap if cursor.col > start.col => {
+                    cursor = Position::new(start.col, cursor.row + 1);
+                }
+                Wrap::Wrap => {}
+            }
+        }
+        if screen.contains(&cursor) {
+            screen.write_cell(&cursor, cell);
+        }
+        cursor.col += width;
+    }
+    cursor
+}
+
+/**
+ * Draws a row of the cell rightwards from the position, `len` columns long.
+ */
+pub fn hline(screen: &mut Screen, from: &Position, len: i32, cell: &Cell) {
+    let step = cell.width().max(1) as i32;
+    for col in (0..len).step_by(step as usize) {
+        let position = *from + Position::new(col, 0);
+        if screen.contains(&position) {
+            screen.write_cell(&position, cell.clone());
+        }
+    }
+}
+
+/**
+ * Draws a column of the cell downwards from the position, `len` rows long.
+ */
+pub fn vline(screen: &mut Screen, from: &Position, len: i32, cell: &Cell) {
+    for row in 0..len {
+        let position = *from + Position::new(0, row);
+        if screen.contains(&position) {
+            screen.write_cell(&position, cell.clone());
+        }
+    }
+}
+
+/**
+ * Draws the border of a rectangle whose top left corner is at the position and which is `size`
+ * columns and rows large, border included. The inside is left as it was.
+ */
+pub fn rect(
+    screen: &mut Screen,
+    top_left: &Position,
+    size: Position,
+    border: &Border,
+    style: Style,
+) {
+    let Position { col: w, row: h } = size;
+    if w <= 0 || h <= 0 {
+        return;
+    }
+    let Position {
+        col: left,
+        row: top,
+    } = *top_left;
+    let right = left + w - 1;
+    let bottom = top + h - 1;
+    let horizontal = Cell::styled(border.horizontal, style);
+    let vertical = Cell::styled(border.vertical, style);
+
+    hline(screen, &(left + 1, top).into(), w - 2, &horizontal);
+    hline(screen, &(left + 1, bottom).into(), w - 2, &horizontal);
+    vline(screen, &(left, top + 1).into(), h - 2, &vertical);
+    vline(screen, &(right, top + 1).into(), h - 2, &vertical);
+
+    let corners = [
+        ((right, bottom), border.bottom_right),
+        ((left, bottom), border.bottom_left),
+        ((right, top), border.top_right),
+        ((left, top), border.top_left),
+    ];
+    for (position, ch) in corners.iter() {
+        let position: Position = (*position).into();
+        if screen.contains(&position) {
+            screen.write_styled(&position, *ch, style);
+        }
+    }
+}
+
+/**
+ * Replaces the area of identical cells around the position, connected up, down, left or right,
+ * with the cell. Buffered writes count, so shapes drawn before a flush can be filled. Returns the
+ * number of cells filled. The cell should be a single column wide.
+ */
+pub fn flood_fill(screen: &mut Screen, start: &Position, cell: &Cell) -> usize {
+    let target = screen.read_pending(start);
+    if !screen.contains(start) || target == *cell {
+        return 0;
+    }
+
+    let mut filled = 0;
+    let mut stack = vec![*start];
+    while let Some(position) = stack.pop() {
+        if !screen.contains(&position) || screen.read_pending(&position) != target {
+            continue;
+        }
+        screen.write_cell(&position, cell.clone());
+        filled += 1;
+        for direction in Position::DIRECTIONS.iter() {
+            stack.push(position + *direction);
+        }
+    }
+    filled
+}
+
+/**
+ * Draws a straight line of the cell between the two positions, both ends included, using
+ * Bresenham's algorithm.
+ */
+pub fn line(screen: &mut Screen, from: &Position, to: &Position, cell: &Cell) {
+    let dc = (to.col - from.col).abs();
+    let dr = -(to.row - from.row).abs();
+    let step_col = if from.col < to.col { 1 } else { -1 };
+    let step_row = if from.row < to.row { 1 } else { -1 };
+
+    let mut position = *from;
+    let mut error = dc + dr;
+    loop {
+        if screen.contains(&position) {
+            screen.write_cell(&position, cell.clone());
+        }
+        if position == *to {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dr {
+            error += dr;
+            position.col += step_col;
+        }
+        if doubled <= dc {
+            error += dc;
+            position.row += step_row;
+        }
+    }
+}
+
+#[test]
+fn test_write_str() {
+    let plain = Style::default();
+    let mut screen = Screen::with_size((5, 4).into());
+    let rows = |screen: &Screen| -> Vec<String> {
+        (0..screen.rows)
+            .map(|r| {
+                (0..screen.cols)
+                    .map(|c| screen.read(&(c, r).into()))
+                    .collect()
+            })
+            .collect()
+    };
+
+    let end = write_str(&mut screen, &(1, 0).into(), "hello\nyou", plain, Wrap::Clip);
+    assert_eq!(end, (4, 1).into());
+    let end = write_str(&mut screen, &(1, 2).into(), "wrapped", plain, Wrap::Wrap);
+    assert_eq!(end, (4, 3).into());
+    screen.flush();
+    assert_eq!(rows(&screen), vec![" hell", " you ", " wrap", " ped "]);
+
+    // Wide graphemes move to the next row whole.
+    screen.clear();
+    write_str(&mut screen, &(0, 0).into(), "ab漢字", plain, Wrap::Wrap);
+    screen.flush();
+    assert_eq!(
+        screen.read_cell(&(2, 0).into()),
+        Cell::with_grapheme("漢", plain)
+    );
+    assert_eq!(screen.read(&(4, 0).into()), ' ');
+    assert_eq!(
+        screen.read_cell(&(0, 1).into()),
+        Cell::with_grapheme("字", plain)
+    );
+}
+
+#[test]
+fn test_shapes() {
+    let plain = Style::default();
+    let mut screen = Screen::with_size((6, 4).into());
+    let rows = |screen: &Screen| -> Vec<String> {
+        (0..screen.rows)
+            .map(|r| {
+                (0..screen.cols)
+                    .map(|c| screen.read(&(c, r).into()))
+                    .collect()
+            })
+            .collect()
+    };
+
+    rect(
+        &mut screen,
+        &(0, 0).into(),
+        (4, 3).into(),
+        &Border::LIGHT,
+        plain,
+    );
+    // Clipped by the bottom right corner of the screen.
+    rect(
+        &mut screen,
+        &(4, 2).into(),
+        (3, 3).into(),
+        &Border::ASCII,
+        plain,
+    );
+    assert_eq!(flood_fill(&mut screen, &(1, 1).into(), &Cell::new('#')), 2);
+    screen.flush();
+    assert_eq!(rows(&screen), vec!["┌──┐  ", "│##│  ", "└──┘+-", "    | "]);
+
+    // The fill stops at the shapes.
+    assert_eq!(flood_fill(&mut screen, &(5, 0).into(), &Cell::new('.')), 4);
+    assert_eq!(flood_fill(&mut screen, &(5, 0).into(), &Cell::new('.')), 0);
+    screen.flush();
+    assert_eq!(rows(&screen), vec!["┌──┐..", "│##│..", "└──┘+-", "    | "]);
+
+    screen.clear();
+    hline(&mut screen, &(1, 0).into(), 9, &Cell::new('-'));
+    vline(&mut screen, &(0, 0).into(), 9, &Cell::new('|'));
+    line(
+        &mut screen,
+        &(5, 3).into(),
+        &(1, 1).into(),
+        &Cell::new('\\'),
+    );
+    screen.flush();
+    assert_eq!(
+        rows(&screen),
+        vec!["|-----", "|\\\\   ", "|  \\\\ ", "|    \\"]
+    );
+}
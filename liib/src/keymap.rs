@@ -0,0 +1,375 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::position::Position;
+
+/**
+ * What a key binding does in the editor, see [crate::term::perform].
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Moves the cursor one cell in the direction.
+    Step(Position),
+    /// Moves the cursor to the edge of the screen in the direction.
+    JumpEnd(Position),
+    /// Moves the cursor to where the cells change between blank and not in the direction.
+    JumpBoundary(Position),
+    /// Writes the character under the cursor.
+    Write(char),
+    /// Rings the bell as many times as the cursor is far from the top left.
+    Bells,
+    Quit,
+    /// Does nothing, which also unbinds keys in a config.
+    None,
+}
+
+/**
+ * Maps keys, or sequences of them, to [Action]s. Keys are fed one at a time, and a sequence
+ * waits until it is complete or broken.
+ *
+ * A key pressed with modifiers that aren't bound falls back to its binding without modifiers,
+ * and a character key that isn't bound at all writes the character.
+ */
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyEvent>, Action>,
+    pending: Vec<KeyEvent>,
+}
+
+/**
+ * A line of a keymap config that could not be understood.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for KeymapError {}
+
+/**
+ * The editor's bindings: arrows step, with Ctrl they jump to the edge and with Alt to the next
+ * boundary. Esc rings the bells and Ctrl-C quits.
+ */
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap::empty();
+        for &(name, direction) in DIRECTIONS.iter() {
+            let code = parse_code(name).unwrap();
+            let key = |modifiers| [KeyEvent::new(code, modifiers)];
+            keymap.bind(&key(KeyModifiers::NONE), Action::Step(direction));
+            keymap.bind(&key(KeyModifiers::CONTROL), Action::JumpEnd(direction));
+            keymap.bind(&key(KeyModifiers::ALT), Action::JumpBoundary(direction));
+        }
+        keymap.bind(
+            &[KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)],
+            Action::Bells,
+        );
+        keymap.bind(
+            &[KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)],
+            Action::Quit,
+        );
+        keymap
+    }
+}
+
+const DIRECTIONS: [(&str, Position); 4] = [
+    ("left", Position::LEFT),
+    ("right", Position::RIGHT),
+    ("up", Position::UP),
+    ("down", Position::DOWN),
+];
+
+impl Keymap {
+    /**
+     * A keymap without bindings, where characters still write themselves.
+     */
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn bind(&mut self, keys: &[KeyEvent], action: Action) {
+        self.bindings.insert(keys.to_vec(), action);
+    }
+
+    pub fn unbind(&mut self, keys: &[KeyEvent]) {
+        self.bindings.remove(keys);
+    }
+
+    /**
+     * The default keymap changed by the config, one binding per line:
+     *
+     * ```text
+     * # Comments start with a hash.
+     * ctrl-h = step left
+     * "g g" = jump-end up
+     * esc = none
+     * ```
+     *
+     * Keys are a character or a name like `left`, `esc`, `enter`, `space` or `f1`, after any of
+     * `ctrl-`, `alt-` and `shift-`. A sequence is keys separated by spaces. The actions are
+     * `step`, `jump-end` and `jump-boundary` with a direction, `write` with a character, `bells`,
+     * `quit` and `none`. Either side may be quoted, so simple configs are valid TOML too.
+     */
+    pub fn parse(text: &str) -> Result<Keymap, KeymapError> {
+        let mut keymap = Keymap::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| KeymapError {
+                line: i + 1,
+                message,
+            };
+
+            let (keys, action) = match line.find('=') {
+                Some(at) => (unquote(&line[..at]), unquote(&line[at + 1..])),
+                None => return Err(error(format!("expected `keys = action`, found `{}`", line))),
+            };
+            let keys = keys
+                .split_whitespace()
+                .map(|key| parse_key(key).ok_or_else(|| error(format!("unknown key `{}`", key))))
+                .collect::<Result<Vec<KeyEvent>, KeymapError>>()?;
+            if keys.is_empty() {
+                return Err(error("no keys to bind".to_string()));
+            }
+            let action = parse_action(action)
+                .ok_or_else(|| error(format!("unknown action `{}`", action)))?;
+            keymap.bind(&keys, action);
+        }
+        Ok(keymap)
+    }
+
+    /**
+     * Parses the config file as in [Keymap::parse]. Mistakes in it are reported as invalid data.
+     */
+    pub fn load_file(path: impl AsRef<Path>) -> io::Result<Keymap> {
+        Keymap::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /**
+     * Feeds the next key pressed. Returns None while it starts a longer sequence, otherwise the
+     * action, which is [Action::None] for keys that aren't bound.
+     */
+    pub fn feed(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending.push(key);
+        if let Some(action) = self.bindings.get(&self.pending) {
+            self.pending.clear();
+            return Some(*action);
+        }
+        let pending = &self.pending;
+        if self
+            .bindings
+            .keys()
+            .any(|keys| keys.len() > pending.len() && keys.starts_with(pending))
+        {
+            return None;
+        }
+
+        // A broken sequence is dropped, but its last key may start another.
+        if self.pending.len() > 1 {
+            self.pending.clear();
+            return self.feed(key);
+        }
+        self.pending.clear();
+
+        let plain = KeyEvent::new(key.code, KeyModifiers::NONE);
+        Some(match (self.bindings.get(&vec![plain]), key.code) {
+            (Some(action), _) => *action,
+            (None, KeyCode::Char(ch)) => Action::Write(ch),
+            (None, _) => Action::None,
+        })
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+/**
+ * Parses a key like `ctrl-left` or `alt-shift-x`.
+ */
+fn parse_key(key: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = key;
+    // A lone `-` is the minus key, not a modifier.
+    while let Some(at) = rest.find('-').filter(|&at| at > 0) {
+        modifiers |= match &rest[..at] {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = &rest[at + 1..];
+    }
+    Some(KeyEvent::new(parse_code(rest)?, modifiers))
+}
+
+fn parse_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(ch));
+    }
+    Some(match name {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        _ => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+    })
+}
+
+fn parse_action(action: &str) -> Option<Action> {
+    let mut words = action.split_whitespace();
+    let name = words.next()?;
+    let argument = words.next();
+    if words.next().is_some() {
+        return None;
+    }
+    let direction = || {
+        let argument = argument?;
+        DIRECTIONS
+            .iter()
+            .find(|(name, _)| *name == argument)
+            .map(|(_, direction)| *direction)
+    };
+
+    Some(match (name, argument) {
+        ("step", _) => Action::Step(direction()?),
+        ("jump-end", _) => Action::JumpEnd(direction()?),
+        ("jump-boundary", _) => Action::JumpBoundary(direction()?),
+        ("write", Some(argument)) => match parse_code(argument)? {
+            KeyCode::Char(ch) => Action::Write(ch),
+            _ => return None,
+        },
+        ("bells", None) => Action::Bells,
+        ("quit", None) => Action::Quit,
+        ("none", None) => Action::None,
+        _ => return None,
+    })
+}
+
+#[test]
+fn test_default() {
+    let key = |code: KeyCode, modifiers: KeyModifiers| KeyEvent::new(code, modifiers);
+    let mut keymap = Keymap::default();
+
+    assert_eq!(
+        keymap.feed(key(KeyCode::Left, KeyModifiers::NONE)),
+        Some(Action::Step(Position::LEFT))
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Up, KeyModifiers::CONTROL)),
+        Some(Action::JumpEnd(Position::UP))
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Down, KeyModifiers::ALT)),
+        Some(Action::JumpBoundary(Position::DOWN))
+    );
+    // Falls back to the key without modifiers.
+    assert_eq!(
+        keymap.feed(key(KeyCode::Right, KeyModifiers::SHIFT)),
+        Some(Action::Step(Position::RIGHT))
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Char('C'), KeyModifiers::SHIFT)),
+        Some(Action::Write('C'))
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Esc, KeyModifiers::SHIFT)),
+        Some(Action::Bells)
+    );
+    assert_eq!(
+        keymap.feed(key(KeyCode::Tab, KeyModifiers::NONE)),
+        Some(Action::None)
+    );
+}
+
+#[test]
+fn test_parse() {
+    let key = |code: KeyCode, modifiers: KeyModifiers| KeyEvent::new(code, modifiers);
+    let config = r#"
+        # Vi-ish.
+        h = step left
+        "ctrl-alt-x" = "write #"
+        "g g" = jump-end up
+        esc = none
+        space = write space
+    "#;
+    let mut keymap = Keymap::parse(config).unwrap();
+
+    let h = key(KeyCode::Char('h'), KeyModifiers::NONE);
+    let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+    assert_eq!(keymap.feed(h), Some(Action::Step(Position::LEFT)));
+    assert_eq!(
+        keymap.feed(key(
+            KeyCode::Char('x'),
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )),
+        Some(Action::Write('#'))
+    );
+    assert_eq!(keymap.feed(g), None);
+    assert_eq!(keymap.feed(g), Some(Action::JumpEnd(Position::UP)));
+    // A broken sequence goes on with the key that broke it.
+    assert_eq!(keymap.feed(g), None);
+    assert_eq!(keymap.feed(h), Some(Action::Step(Position::LEFT)));
+    assert_eq!(
+        keymap.feed(key(KeyCode::Esc, KeyModifiers::NONE)),
+        Some(Action::None)
+    );
+    // The defaults are kept.
+    assert_eq!(
+        keymap.feed(key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+
+    assert_eq!(
+        Keymap::parse("ctrl-left = step\n").unwrap_err(),
+        KeymapError {
+            line: 1,
+            message: "unknown action `step`".to_string()
+        }
+    );
+    assert_eq!(
+        Keymap::parse("\nhyper-x = quit").unwrap_err().to_string(),
+        "line 2: unknown key `hyper-x`"
+    );
+    assert!(Keymap::parse("quit").is_err());
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use crate::position::{Position, Rect};
+
+/**
+ * How much of the row or column being split a part takes.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Exactly this many cells.
+    Fixed(i32),
+    /// This percentage of the whole, rounded down.
+    Percent(u16),
+    /// At least this many cells, and a share of what is left like `Fill(1)`.
+    Min(i32),
+    /// A share of what is left like `Fill(1)`, but no more than this many cells.
+    Max(i32),
+    /// A share of what is left after the other parts, in proportion to the weight.
+    Fill(u16),
+}
+
+/**
+ * Which way a [Layout] lays out its parts.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// Side by side, left to right.
+    Columns,
+    /// Stacked, top to bottom.
+    Rows,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Pane(String),
+    Split(Layout),
+}
+
+/**
+ * Declares how an area is split into named panes, as rows or columns that can themselves be
+ * split. Solving it for an area, like the terminal's after every resize, gives each pane's
+ * [Rect].
+ *
+ * ```
+ * use liib::layout::{Constraint, Layout};
+ *
+ * let layout = Layout::columns()
+ *     .pane("gutter", Constraint::Fixed(5))
+ *     .pane("main", Constraint::Fill(1));
+ * let panes = layout.solve(&(80, 24).into());
+ * assert_eq!(panes["main"].origin, (5, 0).into());
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct Layout {
+    direction: Direction,
+    parts: Vec<(Constraint, Part)>,
+}
+
+impl Layout {
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            parts: Vec::new(),
+        }
+    }
+
+    pub fn columns() -> Self {
+        Self::new(Direction::Columns)
+    }
+
+    pub fn rows() -> Self {
+        Self::new(Direction::Rows)
+    }
+
+    /**
+     * Adds a pane after the others.
+     */
+    pub fn pane(mut self, name: &str, constraint: Constraint) -> Self {
+        self.parts.push((constraint, Part::Pane(name.to_string())));
+        self
+    }
+
+    /**
+     * Adds a part after the others that is split again by the layout.
+     */
+    pub fn split(mut self, constraint: Constraint, layout: Layout) -> Self {
+        self.parts.push((constraint, Part::Split(layout)));
+        self
+    }
+
+    /**
+     * The area of every pane, nested ones included, by name.
+     */
+    pub fn solve(&self, area: &Rect) -> HashMap<String, Rect> {
+        let mut panes = HashMap::new();
+        self.solve_into(area, &mut panes);
+        panes
+    }
+
+    /**
+     * The areas of the parts directly in this layout, in order.
+     */
+    pub fn areas(&self, area: &Rect) -> Vec<Rect> {
+        let (total, across) = match self.direction {
+            Direction::Columns => (area.size.col, area.size.row),
+            Direction::Rows => (area.size.row, area.size.col),
+        };
+        let constraints: Vec<Constraint> = self.parts.iter().map(|(c, _)| *c).collect();
+
+        let mut offset = 0;
+        sizes(&constraints, total.max(0))
+            .into_iter()
+            .map(|len| {
+                let (origin, size) = match self.direction {
+                    Direction::Columns => (Position::new(offset, 0), Position::new(len, across)),
+                    Direction::Rows => (Position::new(0, offset), Position::new(across, len)),
+                };
+                offset += len;
+                Rect::new(area.origin + origin, size)
+            })
+            .collect()
+    }
+
+    fn solve_into(&self, area: &Rect, panes: &mut HashMap<String, Rect>) {
+        for ((_, part), rect) in self.parts.iter().zip(self.areas(area)) {
+            match part {
+                Part::Pane(name) => {
+                    panes.insert(name.clone(), rect);
+                }
+                Part::Split(layout) => layout.solve_into(&rect, panes),
+            }
+        }
+    }
+}
+
+/**
+ * The length of each part out of the total. Fixed, percentage and minimum lengths come first, in
+ * order, with the later ones cut short when they don't all fit. What is left is shared between
+ * the growing parts by weight, except that a maximum is never passed. Any remainder goes to the
+ * first growing parts.
+ */
+fn sizes(constraints: &[Constraint], total: i32) -> Vec<i32> {
+    let mut remaining = total;
+    let mut sizes: Vec<i32> = constraints
+        .iter()
+        .map(|constraint| {
+            let wanted = match *constraint {
+                Constraint::Fixed(n) | Constraint::Min(n) => n.max(0),
+                Constraint::Percent(p) => total * p as i32 / 100,
+                Constraint::Max(_) | Constraint::Fill(_) => 0,
+            };
+            let size = wanted.min(remaining);
+            remaining -= size;
+            size
+        })
+        .collect();
+
+    let weight = |constraint: &Constraint| match *constraint {
+        Constraint::Min(_) | Constraint::Max(_) => 1,
+        Constraint::Fill(w) => w as i32,
+        Constraint::Fixed(_) | Constraint::Percent(_) => 0,
+    };
+    let mut growing: Vec<usize> = (0..constraints.len())
+        .filter(|&i| weight(&constraints[i]) > 0)
+        .collect();
+
+    // Parts that reach their maximum drop out and the rest share again.
+    while remaining > 0 && !growing.is_empty() {
+        let weights: i32 = growing.iter().map(|&i| weight(&constraints[i])).sum();
+        let mut shares: Vec<i32> = growing
+            .iter()
+            .map(|&i| remaining * weight(&constraints[i]) / weights)
+            .collect();
+        let mut left = remaining - shares.iter().sum::<i32>();
+        for share in shares.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            *share += 1;
+            left -= 1;
+        }
+
+        let mut capped = false;
+        for (&i, share) in growing.iter().zip(shares.iter_mut()) {
+            if let Constraint::Max(max) = constraints[i] {
+                if sizes[i] + *share >= max {
+                    *share = (max - sizes[i]).max(0);
+                    capped = true;
+                }
+            }
+            sizes[i] += *share;
+            remaining -= *share;
+        }
+        if !capped {
+            break;
+        }
+        growing.retain(|&i| match constraints[i] {
+            Constraint::Max(max) => sizes[i] < max,
+            _ => true,
+        });
+    }
+    sizes
+}
+
+#[test]
+fn test_sizes() {
+    use Constraint::*;
+
+    assert_eq!(sizes(&[Fixed(5), Fill(1)], 80), vec![5, 75]);
+    assert_eq!(
+        sizes(&[Percent(25), Fill(1), Fill(2)], 100),
+        vec![25, 25, 50]
+    );
+    // The remainder goes to the first.
+    assert_eq!(sizes(&[Fill(1), Fill(1), Fill(1)], 10), vec![4, 3, 3]);
+    assert_eq!(sizes(&[Min(10), Fill(1)], 30), vec![20, 10]);
+    assert_eq!(sizes(&[Max(4), Fill(1)], 30), vec![4, 26]);
+    assert_eq!(sizes(&[Max(4), Max(6)], 30), vec![4, 6]);
+    // Too small for everything.
+    assert_eq!(sizes(&[Fixed(5), Min(10), Fill(1)], 12), vec![5, 7, 0]);
+    assert_eq!(sizes(&[Fixed(5)], 0), vec![0]);
+    assert_eq!(sizes(&[], 10), Vec::<i32>::new());
+}
+
+#[test]
+fn test_layout() {
+    let layout = Layout::rows()
+        .pane("title", Constraint::Fixed(1))
+        .split(
+            Constraint::Fill(1),
+            Layout::columns()
+                .pane("left", Constraint::Percent(50))
+                .pane("right", Constraint::Fill(1)),
+        )
+        .pane("status", Constraint::Fixed(1));
+
+    let panes = layout.solve(&Rect::from((81, 24)));
+    assert_eq!(panes.len(), 4);
+    assert_eq!(panes["title"], Rect::new((0, 0).into(), (81, 1).into()));
+    assert_eq!(panes["left"], Rect::new((0, 1).into(), (40, 22).into()));
+    assert_eq!(panes["right"], Rect::new((40, 1).into(), (41, 22).into()));
+    assert_eq!(panes["status"], Rect::new((0, 23).into(), (81, 1).into()));
+
+    // Solved again after a resize.
+    let panes = layout.solve(&Rect::from((10, 3)));
+    assert_eq!(panes["right"], Rect::new((5, 1).into(), (5, 1).into()));
+    assert_eq!(panes["status"].origin, (0, 2).into());
+}
@@ -1,5 +1,17 @@
+pub mod app;
+pub mod backend;
+pub mod cast;
+pub mod cell;
+pub mod color;
+pub mod compositor;
 pub mod cro;
+pub mod draw;
+pub mod keymap;
+pub mod layout;
 pub mod position;
 pub mod ringer;
+pub mod save;
 pub mod screen;
+pub mod snapshot;
 pub mod term;
+pub mod widget;
@@ -2,6 +2,7 @@ use core::cmp::Ordering;
 use core::fmt;
 use core::hash::Hash;
 use std::convert;
+use std::error::Error;
 use std::ops;
 
 pub type Visible = (u16, u16);
@@ -13,10 +14,100 @@ pub struct Position {
 }
 
 impl Position {
+    pub const UP: Position = Position { col: 0, row: -1 };
+    pub const DOWN: Position = Position { col: 0, row: 1 };
+    pub const LEFT: Position = Position { col: -1, row: 0 };
+    pub const RIGHT: Position = Position { col: 1, row: 0 };
+    /**
+     * The unit steps to the neighbours that share a side, clockwise from up.
+     */
+    pub const DIRECTIONS: [Position; 4] = [
+        Position::UP,
+        Position::RIGHT,
+        Position::DOWN,
+        Position::LEFT,
+    ];
+
     pub fn new(col: i32, row: i32) -> Self {
         Self { col, row }
     }
 
+    pub fn checked_add(&self, other: Position) -> Option<Position> {
+        Some(Position::new(
+            self.col.checked_add(other.col)?,
+            self.row.checked_add(other.row)?,
+        ))
+    }
+
+    pub fn checked_sub(&self, other: Position) -> Option<Position> {
+        Some(Position::new(
+            self.col.checked_sub(other.col)?,
+            self.row.checked_sub(other.row)?,
+        ))
+    }
+
+    pub fn checked_mul(&self, scalar: i32) -> Option<Position> {
+        Some(Position::new(
+            self.col.checked_mul(scalar)?,
+            self.row.checked_mul(scalar)?,
+        ))
+    }
+
+    /**
+     * None when dividing by 0, or on overflow.
+     */
+    pub fn checked_div(&self, scalar: i32) -> Option<Position> {
+        Some(Position::new(
+            self.col.checked_div(scalar)?,
+            self.row.checked_div(scalar)?,
+        ))
+    }
+
+    pub fn saturating_add(&self, other: Position) -> Position {
+        Position::new(
+            self.col.saturating_add(other.col),
+            self.row.saturating_add(other.row),
+        )
+    }
+
+    pub fn saturating_sub(&self, other: Position) -> Position {
+        Position::new(
+            self.col.saturating_sub(other.col),
+            self.row.saturating_sub(other.row),
+        )
+    }
+
+    pub fn saturating_mul(&self, scalar: i32) -> Position {
+        Position::new(
+            self.col.saturating_mul(scalar),
+            self.row.saturating_mul(scalar),
+        )
+    }
+
+    /**
+     * The number of steps up, down, left or right to the other position.
+     */
+    pub fn manhattan(&self, other: &Position) -> u32 {
+        self.col.abs_diff(other.col) + self.row.abs_diff(other.row)
+    }
+
+    /**
+     * The number of steps to the other position when diagonal steps are allowed too.
+     */
+    pub fn chebyshev(&self, other: &Position) -> u32 {
+        self.col
+            .abs_diff(other.col)
+            .max(self.row.abs_diff(other.row))
+    }
+
+    /**
+     * The position as terminal coordinates, with negatives floored to 0 and anything past the
+     * largest clamped to it. See the `TryFrom` conversion to find out instead.
+     */
+    pub fn saturating_visible(&self) -> Visible {
+        (saturate(self.col), saturate(self.row))
+    }
+
     pub fn clamp(&self, min: Position, max: Position) -> Self {
         Self {
             col: self.col.clamp(min.col, max.col),
@@ -84,21 +175,84 @@ impl ops::Add for Position {
     }
 }
 
-impl convert::From<Position> for Visible {
-    fn from(p: Position) -> Self {
-        let Position { col, row } = p;
-        (floorcast(col), floorcast(row))
+impl ops::AddAssign for Position {
+    fn add_assign(&mut self, other: Position) {
+        *self = *self + other;
+    }
+}
+
+impl ops::SubAssign for Position {
+    fn sub_assign(&mut self, other: Position) {
+        *self = *self - other;
+    }
+}
+
+impl ops::Neg for Position {
+    type Output = Position;
+
+    fn neg(self) -> Position {
+        Position {
+            col: -self.col,
+            row: -self.row,
+        }
+    }
+}
+
+impl ops::Mul<i32> for Position {
+    type Output = Position;
+
+    fn mul(self, scalar: i32) -> Position {
+        Position {
+            col: self.col * scalar,
+            row: self.row * scalar,
+        }
+    }
+}
+
+/**
+ * Rounds towards 0, like integer division does.
+ */
+impl ops::Div<i32> for Position {
+    type Output = Position;
+
+    fn div(self, scalar: i32) -> Position {
+        Position {
+            col: self.col / scalar,
+            row: self.row / scalar,
+        }
     }
 }
 
-fn floorcast(signed: i32) -> u16 {
-    if signed < 0 {
-        0
-    } else {
-        signed as u16
+/**
+ * A position that can't be shown in a terminal, because it is negative or too large.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OutOfRange(pub Position);
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is outside the range of terminal coordinates", self.0)
     }
 }
 
+impl Error for OutOfRange {}
+
+impl convert::TryFrom<Position> for Visible {
+    type Error = OutOfRange;
+
+    fn try_from(p: Position) -> Result<Self, Self::Error> {
+        let Position { col, row } = p;
+        match (u16::try_from(col), u16::try_from(row)) {
+            (Ok(col), Ok(row)) => Ok((col, row)),
+            _ => Err(OutOfRange(p)),
+        }
+    }
+}
+
+fn saturate(signed: i32) -> u16 {
+    signed.clamp(0, u16::MAX as i32) as u16
+}
+
 impl convert::From<Visible> for Position {
     fn from(t: Visible) -> Self {
         let (col, row) = t;
@@ -124,6 +278,193 @@ impl convert::From<(i32, i32)> for Position {
     }
 }
 
+/**
+ * An area of cells: the `size` columns and rows starting at `origin`. A rect with no columns or
+ * no rows is empty and contains nothing.
+ */
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub origin: Position,
+    pub size: Position,
+}
+
+impl Rect {
+    pub fn new(origin: Position, size: Position) -> Self {
+        Self { origin, size }
+    }
+
+    /**
+     * The rect from `min` up to, but not including, `end`.
+     */
+    pub fn from_corners(min: Position, end: Position) -> Self {
+        Self::new(min, end - min)
+    }
+
+    /**
+     * The position just past the bottom right corner.
+     */
+    pub fn end(&self) -> Position {
+        self.origin + self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.col <= 0 || self.size.row <= 0
+    }
+
+    pub fn area(&self) -> i32 {
+        if self.is_empty() {
+            0
+        } else {
+            self.size.col * self.size.row
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        let end = self.end();
+        self.origin.col <= position.col
+            && position.col < end.col
+            && self.origin.row <= position.row
+            && position.row < end.row
+    }
+
+    /**
+     * The closest position inside the rect, or the origin if it is empty.
+     */
+    pub fn clamp(&self, position: &Position) -> Position {
+        let last = self.end() - Position::new(1, 1);
+        position.clamp(
+            self.origin,
+            Position::new(last.col.max(self.origin.col), last.row.max(self.origin.row)),
+        )
+    }
+
+    /**
+     * The area both rects cover, which is empty if they don't overlap.
+     */
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let (end, other_end) = (self.end(), other.end());
+        let min = Position::new(
+            self.origin.col.max(other.origin.col),
+            self.origin.row.max(other.origin.row),
+        );
+        let end = Position::new(end.col.min(other_end.col), end.row.min(other_end.row));
+        if min.col < end.col && min.row < end.row {
+            Rect::from_corners(min, end)
+        } else {
+            Rect::new(min, Position::new(0, 0))
+        }
+    }
+
+    /**
+     * The smallest rect covering both. Empty rects are left out.
+     */
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let (end, other_end) = (self.end(), other.end());
+        Rect::from_corners(
+            Position::new(
+                self.origin.col.min(other.origin.col),
+                self.origin.row.min(other.origin.row),
+            ),
+            Position::new(end.col.max(other_end.col), end.row.max(other_end.row)),
+        )
+    }
+
+    /**
+     * Shrinks the rect by the margin on every side, or grows it if negative. Never smaller than
+     * empty.
+     */
+    pub fn inset(&self, margin: i32) -> Rect {
+        Rect::new(
+            self.origin + Position::new(margin, margin),
+            Position::new(
+                (self.size.col - 2 * margin).max(0),
+                (self.size.row - 2 * margin).max(0),
+            ),
+        )
+    }
+
+    /**
+     * Splits into the columns left of `col` and the rest, `col` counting from the origin and
+     * kept within the rect.
+     */
+    pub fn split_cols(&self, col: i32) -> (Rect, Rect) {
+        let col = col.clamp(0, self.size.col.max(0));
+        (
+            Rect::new(self.origin, Position::new(col, self.size.row)),
+            Rect::new(
+                self.origin + Position::new(col, 0),
+                Position::new(self.size.col - col, self.size.row),
+            ),
+        )
+    }
+
+    /**
+     * Splits into the rows above `row` and the rest, `row` counting from the origin and kept
+     * within the rect.
+     */
+    pub fn split_rows(&self, row: i32) -> (Rect, Rect) {
+        let row = row.clamp(0, self.size.row.max(0));
+        (
+            Rect::new(self.origin, Position::new(self.size.col, row)),
+            Rect::new(
+                self.origin + Position::new(0, row),
+                Position::new(self.size.col, self.size.row - row),
+            ),
+        )
+    }
+
+    /**
+     * The positions inside, in row-major order like [Position]'s `Ord`.
+     */
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let Rect { origin, size } = *self;
+        let (cols, rows) = (size.col.max(0), size.row.max(0));
+        (0..rows).flat_map(move |row| {
+            (0..cols).map(move |col| Position::new(origin.col + col, origin.row + row))
+        })
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.origin, self.size)
+    }
+}
+
+/**
+ * A rect of the given size at the top left, like the terminal's.
+ */
+impl convert::From<Visible> for Rect {
+    fn from(size: Visible) -> Self {
+        Rect::new(Position::new(0, 0), size.into())
+    }
+}
+
+impl convert::From<(Visible, Visible)> for Rect {
+    fn from(t: (Visible, Visible)) -> Self {
+        let (origin, size) = t;
+        Rect::new(origin.into(), size.into())
+    }
+}
+
+/**
+ * The origin and size, saturated like [Position#saturating_visible].
+ */
+impl convert::From<Rect> for (Visible, Visible) {
+    fn from(rect: Rect) -> Self {
+        (
+            rect.origin.saturating_visible(),
+            rect.size.saturating_visible(),
+        )
+    }
+}
+
 #[test]
 fn test_position() {
     let small = Position::new(1, 2);
@@ -150,3 +491,106 @@ fn test_position() {
     assert!(hash(&small) != hash(&big));
     assert!(hash(&small) == hash(&small));
 }
+
+#[test]
+fn test_arithmetic() {
+    use std::convert::TryFrom;
+
+    let mut p = Position::new(3, -4);
+    assert_eq!(p * 2, (6, -8).into());
+    assert_eq!(p / 2, (1, -2).into());
+    assert_eq!(-p, (-3, 4).into());
+    p += Position::RIGHT;
+    assert_eq!(p, (4, -4).into());
+    p -= Position::UP * 4;
+    assert_eq!(p, (4, 0).into());
+    assert_eq!(
+        Position::DIRECTIONS
+            .iter()
+            .fold(Position::default(), |sum, d| sum + *d),
+        Position::default()
+    );
+
+    let max = Position::new(i32::MAX, 0);
+    assert_eq!(max.checked_add(Position::RIGHT), None);
+    assert_eq!(
+        max.checked_sub(Position::RIGHT),
+        Some((i32::MAX - 1, 0).into())
+    );
+    assert_eq!(max.checked_mul(2), None);
+    assert_eq!(p.checked_div(0), None);
+    assert_eq!(p.checked_div(2), Some((2, 0).into()));
+    assert_eq!(max.saturating_add(Position::RIGHT), max);
+    assert_eq!(
+        Position::new(i32::MIN, 1).saturating_sub((1, 1).into()),
+        (i32::MIN, 0).into()
+    );
+    assert_eq!(max.saturating_mul(-2), (i32::MIN, 0).into());
+
+    let a = Position::new(1, 1);
+    let b = Position::new(4, -1);
+    assert_eq!(a.manhattan(&b), 5);
+    assert_eq!(a.chebyshev(&b), 3);
+    assert_eq!(max.manhattan(&-max), u32::MAX - 1);
+
+    assert_eq!(Visible::try_from(Position::new(3, 65535)), Ok((3, 65535)));
+    let err = Visible::try_from(Position::new(3, 65536)).unwrap_err();
+    assert_eq!(err, OutOfRange((3, 65536).into()));
+    assert_eq!(
+        err.to_string(),
+        "(3, 65536) is outside the range of terminal coordinates"
+    );
+    assert!(Visible::try_from(Position::new(-1, 0)).is_err());
+    assert_eq!(Position::new(-1, 70000).saturating_visible(), (0, 65535));
+}
+
+#[test]
+fn test_rect() {
+    let rect = Rect::new((1, 2).into(), (3, 2).into());
+    assert_eq!(rect.end(), (4, 4).into());
+    assert_eq!(rect.area(), 6);
+    assert!(rect.contains(&(1, 2).into()));
+    assert!(rect.contains(&(3, 3).into()));
+    assert!(!rect.contains(&(4, 3).into()));
+    assert!(!rect.contains(&(0, 2).into()));
+    assert_eq!(rect.clamp(&(9, -9).into()), (3, 2).into());
+
+    let positions: Vec<Position> = rect.positions().collect();
+    assert_eq!(positions.len(), 6);
+    assert_eq!(positions[0], (1, 2).into());
+    assert_eq!(positions[3], (1, 3).into());
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let other = Rect::new((3, 0).into(), (5, 3).into());
+    assert_eq!(
+        rect.intersect(&other),
+        Rect::new((3, 2).into(), (1, 1).into())
+    );
+    assert_eq!(
+        rect.union(&other),
+        Rect::from_corners((1, 0).into(), (8, 4).into())
+    );
+    let apart = Rect::new((9, 9).into(), (1, 1).into());
+    assert!(rect.intersect(&apart).is_empty());
+    assert_eq!(rect.union(&rect.intersect(&apart)), rect);
+
+    assert_eq!(rect.inset(1), Rect::new((2, 3).into(), (1, 0).into()));
+    assert!(rect.inset(1).is_empty());
+    assert_eq!(rect.inset(-1), Rect::new((0, 1).into(), (5, 4).into()));
+    assert_eq!(rect.inset(1).clamp(&(0, 0).into()), (2, 3).into());
+
+    let (left, right) = rect.split_cols(1);
+    assert_eq!(left, Rect::new((1, 2).into(), (1, 2).into()));
+    assert_eq!(right, Rect::new((2, 2).into(), (2, 2).into()));
+    let (top, bottom) = rect.split_rows(5);
+    assert_eq!(top, rect);
+    assert!(bottom.is_empty());
+
+    let visible: (Visible, Visible) = Rect::new((-1, 2).into(), (3, 4).into()).into();
+    assert_eq!(visible, ((0, 2), (3, 4)));
+    assert_eq!(
+        Rect::from(((0, 2), (3, 4))),
+        Rect::new((0, 2).into(), (3, 4).into())
+    );
+    assert_eq!(Rect::from((80, 24)).end(), (80, 24).into());
+}
@@ -0,0 +1,309 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crossterm::queue;
+use crossterm::style::{Attribute, Color, SetAttribute};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::cell::{Cell, Grapheme, Style, BLANK};
+use crate::color::queue_style;
+use crate::position::Position;
+use crate::screen::Screen;
+
+/**
+ * How a screen is written to a file.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Just the characters, with trailing spaces trimmed from every row.
+    Text,
+    /// The characters with the escape sequences that reproduce their colours and attributes.
+    Ansi,
+}
+
+impl Format {
+    /**
+     * Ansi for `.ans` and `.ansi` files, and Text for anything else.
+     */
+    pub fn for_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("ans") | Some("ansi") => Format::Ansi,
+            _ => Format::Text,
+        }
+    }
+}
+
+/**
+ * The flushed content of the screen as plain text, one line per row, with whatever a resize
+ * pushed off the screen.
+ */
+pub fn to_text(screen: &Screen) -> String {
+    let mut text = String::new();
+    for row in rows(screen) {
+        let line: String = row.iter().map(|cell| cell.grapheme.to_string()).collect();
+        text.push_str(line.trim_end_matches(BLANK));
+        text.push('\n');
+    }
+    text
+}
+
+/**
+ * The flushed content of the screen as text with ANSI escapes, one line per row, with whatever
+ * a resize pushed off the screen. Every row starts with the default style, so lines can be
+ * printed on their own.
+ */
+pub fn to_ansi(screen: &Screen) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    for row in rows(screen) {
+        // Blank cells with no style at the end of the row add nothing.
+        let end = row
+            .iter()
+            .rposition(|cell| !cell.is_transparent())
+            .map_or(0, |i| i + 1);
+
+        let mut current = Style::default();
+        for cell in &row[..end] {
+            // Writing to a Vec can't fail.
+            queue_style(&mut out, &current, &cell.style).unwrap();
+            current = cell.style;
+            out.extend_from_slice(cell.grapheme.to_string().as_bytes());
+        }
+        if current != Style::default() {
+            queue!(out, SetAttribute(Attribute::Reset)).unwrap();
+        }
+        out.push(b'\n');
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/**
+ * Parses text saved by [to_text] or [to_ansi] into a screen just big enough to hold it. Only the
+ * escape sequences that set colours and attributes are understood, any other is skipped. The
+ * cells are buffered, so the first flush draws all of them.
+ */
+pub fn load(text: &str) -> Screen {
+    let mut cells: Vec<(Position, Cell)> = Vec::new();
+    let mut style = Style::default();
+    let mut size = Position::new(0, 0);
+
+    for (row, line) in text.lines().enumerate() {
+        let mut col = 0;
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(sequence) = rest.strip_prefix("\x1b[") {
+                // Parameters run until the final byte, between '@' and '~'.
+                rest = match sequence.find(|c: char| ('@'..='~').contains(&c)) {
+                    Some(end) => {
+                        if sequence[end..].starts_with('m') {
+                            apply_sgr(&mut style, &sequence[..end]);
+                        }
+                        &sequence[end + 1..]
+                    }
+                    None => "",
+                };
+            } else if let Some(sequence) = rest.strip_prefix('\x1b') {
+                // Two character escapes.
+                let mut chars = sequence.chars();
+                chars.next();
+                rest = chars.as_str();
+            } else {
+                let end = rest.find('\x1b').unwrap_or(rest.len());
+                for grapheme in rest[..end].graphemes(true) {
+                    let cell = Cell::with_grapheme(grapheme, style);
+                    let width = cell.width() as i32;
+                    cells.push(((col, row as i32).into(), cell));
+                    col += width;
+                }
+                rest = &rest[end..];
+            }
+        }
+        size = Position::new(size.col.max(col), row as i32 + 1);
+    }
+
+    let mut screen = Screen::with_size(size);
+    for (position, cell) in cells {
+        screen.write_cell(&position, cell);
+    }
+    screen
+}
+
+pub fn save_file(screen: &Screen, path: impl AsRef<Path>, format: Format) -> io::Result<()> {
+    let contents = match format {
+        Format::Text => to_text(screen),
+        Format::Ansi => to_ansi(screen),
+    };
+    fs::write(path, contents)
+}
+
+pub fn load_file(path: impl AsRef<Path>) -> io::Result<Screen> {
+    Ok(load(&fs::read_to_string(path)?))
+}
+
+/**
+ * The cells of each row, leaving out continuations. Cells kept off the screen by a resize are
+ * included, except for those above or left of it, so the rows can be longer and more than the
+ * screen's.
+ */
+fn rows(screen: &Screen) -> Vec<Vec<Cell>> {
+    let offscreen: Vec<(Position, &Cell)> = screen
+        .offscreen_cells()
+        .filter(|(position, _)| position.col >= 0 && position.row >= 0)
+        .collect();
+    let cols = offscreen
+        .iter()
+        .map(|(position, cell)| position.col + cell.width() as i32)
+        .fold(screen.cols.max(0), i32::max);
+    let rows = offscreen
+        .iter()
+        .map(|(position, _)| position.row + 1)
+        .fold(screen.rows.max(0), i32::max);
+
+    let mut grid = vec![vec![Cell::default(); cols as usize]; rows as usize];
+    for (position, cell) in screen.cells().chain(offscreen) {
+        let row = &mut grid[position.row as usize];
+        let col = position.col as usize;
+        row[col] = cell.clone();
+        // Off the screen, wide cells are kept without their continuation.
+        if cell.width() == 2 && col + 1 < row.len() {
+            row[col + 1] = Cell {
+                grapheme: Grapheme::Continuation,
+                style: cell.style,
+            };
+        }
+    }
+    grid.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .filter(|cell| !cell.is_continuation())
+                .collect()
+        })
+        .collect()
+}
+
+/**
+ * Applies the parameters of a Select Graphic Rendition sequence, `ESC [ ... m`, to the style.
+ */
+pub(crate) fn apply_sgr(style: &mut Style, parameters: &str) {
+    let mut values = parameters.split(';').map(|v| v.parse::<u8>().unwrap_or(0));
+    while let Some(value) = values.next() {
+        match value {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            22 => style.bold = false,
+            4 => style.underline = true,
+            24 => style.underline = false,
+            7 => style.reverse = true,
+            27 => style.reverse = false,
+            30..=37 => style.fg = indexed_color(value - 30),
+            40..=47 => style.bg = indexed_color(value - 40),
+            90..=97 => style.fg = indexed_color(value - 90 + 8),
+            100..=107 => style.bg = indexed_color(value - 100 + 8),
+            38 => style.fg = extended_color(&mut values),
+            48 => style.bg = extended_color(&mut values),
+            39 => style.fg = Color::Reset,
+            49 => style.bg = Color::Reset,
+            _ => {}
+        }
+    }
+}
+
+/**
+ * Reads a `5;n` or `2;r;g;b` colour, as it follows 38 or 48.
+ */
+fn extended_color(values: &mut impl Iterator<Item = u8>) -> Color {
+    match values.next() {
+        Some(5) => values.next().map_or(Color::Reset, indexed_color),
+        Some(2) => {
+            let mut next = || values.next().unwrap_or(0);
+            Color::Rgb {
+                r: next(),
+                g: next(),
+                b: next(),
+            }
+        }
+        _ => Color::Reset,
+    }
+}
+
+/**
+ * The 256 colour palette entry, using the named colours for the first 16 like crossterm does.
+ */
+fn indexed_color(n: u8) -> Color {
+    Color::parse_ansi(&format!("5;{}", n)).unwrap_or(Color::AnsiValue(n))
+}
+
+#[test]
+fn test_text() {
+    let mut screen = Screen::with_size((6, 3).into());
+    crate::scrite!(
+        &mut screen,
+        (0, 0, 'h'),
+        (1, 0, 'i'),
+        (3, 0, '!'),
+        (2, 2, 'x')
+    );
+    screen.write_grapheme(&(0, 1).into(), "漢", Style::default());
+    screen.flush();
+
+    let text = to_text(&screen);
+    assert_eq!(text, "hi !\n漢\n  x\n");
+
+    let mut loaded = load(&text);
+    loaded.flush();
+    assert_eq!((loaded.cols, loaded.rows), (4, 3));
+    assert_eq!(to_text(&loaded), text);
+    assert!(loaded.read_cell(&(1, 1).into()).is_continuation());
+}
+
+#[test]
+fn test_offscreen() {
+    use crate::screen::Resize;
+
+    let text = "a wide line, 漢字\n\n\nlast\n";
+    let mut screen = load(text);
+    screen.flush();
+    // Like opening it in a smaller terminal.
+    screen.resize((4, 2).into(), Resize::KeepTopLeft);
+    screen.flush();
+    assert_eq!(to_text(&screen), text);
+
+    let styled = Style::default().fg(Color::Red);
+    screen.write_styled(&(1, 0).into(), 'n', styled);
+    screen.flush();
+    let mut loaded = load(&to_ansi(&screen));
+    loaded.flush();
+    assert_eq!(to_text(&loaded), "anwide line, 漢字\n\n\nlast\n");
+    assert_eq!(loaded.read_cell(&(1, 0).into()).style, styled);
+}
+
+#[test]
+fn test_ansi() {
+    let plain = Style::default();
+    let red = plain.fg(Color::Red).bold();
+    let mut screen = Screen::with_size((5, 2).into());
+    screen.write_styled(&(0, 0).into(), 'a', red);
+    screen.write_styled(&(1, 0).into(), 'b', red.underline());
+    screen.write(&(2, 0).into(), 'c');
+    let marked = plain.bg(Color::Rgb { r: 1, g: 2, b: 3 }).reverse();
+    screen.write_styled(&(3, 1).into(), ' ', marked);
+    screen.flush();
+
+    let mut loaded = load(&to_ansi(&screen));
+    loaded.flush();
+    assert_eq!((loaded.cols, loaded.rows), (4, 2));
+    for (position, cell) in screen.cells() {
+        if loaded.contains(&position) {
+            assert_eq!(&loaded.read_cell(&position), cell, "at {}", position);
+        }
+    }
+
+    // Basic colours from other programs.
+    let mut loaded = load("\x1b[31;1mr\x1b[0m\x1b[2Kn");
+    loaded.flush();
+    assert_eq!(
+        loaded.read_cell(&(0, 0).into()).style,
+        plain.fg(Color::DarkRed).bold()
+    );
+    assert_eq!(loaded.read_cell(&(1, 0).into()), Cell::new('n'));
+}
@@ -1,17 +1,56 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
-use crate::position::Position;
+pub use crate::cell::BLANK;
+use crate::cell::{Cell, Grapheme, Style};
+use crate::position::{Position, Rect};
 
-pub const BLANK: char = ' ';
-
-#[derive(Debug)]
+/**
+ * Cells within the bounds are kept in row-major grids, so writing and reading them is an index
+ * and flushing only visits the cells written since the last flush. Cells a resize pushes
+ * outside the bounds are kept sparsely and never flushed, other writes outside them are dropped.
+ */
+#[derive(Debug, Clone)]
 pub struct Screen {
     pub cols: i32,
     pub rows: i32,
-    written: HashMap<Position, char>,
-    buffer: HashMap<Position, char>,
+    written: Vec<Cell>,
+    buffer: Vec<Option<Cell>>,
+    dirty: Vec<usize>,
+    offscreen: HashMap<Position, Cell>,
+    redraw: bool,
+}
+
+/**
+ * How a [Screen] places its content when it is resized.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Resize {
+    /// Content keeps its position and whatever falls outside the new size is dropped.
+    Crop,
+    /// Content keeps its position, and cells pushed off-screen come back if the screen grows.
+    KeepTopLeft,
+    /// Content is shifted so it stays centered, keeping cells pushed off-screen.
+    Recenter,
+}
+
+/**
+ * A change to draw, as returned by [Screen#flush_runs].
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Update {
+    /// All rows moved up by this many rows, or down if negative, with blank rows coming in.
+    Scroll(i32),
+    /// Changed cells next to each other on one row, starting at the position.
+    Run(Position, Vec<Cell>),
 }
 
+/**
+ * Rows a scroll has to save from redrawing to be worth sending.
+ */
+const MIN_SCROLL_GAIN: usize = 2;
+
 impl Default for Screen {
     fn default() -> Self {
         Self::with_size((100, 100).into())
@@ -20,27 +59,136 @@ impl Default for Screen {
 
 impl Screen {
     pub fn with_size(col_row: Position) -> Self {
+        let cols = col_row.col.max(0);
+        let rows = col_row.row.max(0);
+        let area = (cols * rows) as usize;
         Self {
-            cols: col_row.col,
-            rows: col_row.row,
-            written: HashMap::with_capacity(10 * 10),
-            buffer: HashMap::with_capacity(10 * 10),
+            cols,
+            rows,
+            written: vec![Cell::default(); area],
+            buffer: vec![None; area],
+            dirty: Vec::with_capacity(area),
+            offscreen: HashMap::new(),
+            redraw: false,
         }
     }
 
+    /**
+     * Changes the size of the screen, placing the current content according to the policy.
+     * Pending writes are kept. The terminal can't be trusted to still show what was flushed
+     * after a resize, so the next [#flush] returns every visible cell.
+     */
+    pub fn resize(&mut self, col_row: Position, policy: Resize) {
+        let offset = match policy {
+            Resize::Crop | Resize::KeepTopLeft => Position::new(0, 0),
+            Resize::Recenter => Position::new(
+                (col_row.col.max(0) - self.cols) / 2,
+                (col_row.row.max(0) - self.rows) / 2,
+            ),
+        };
+
+        let mut content: Vec<(Position, Cell)> = self.offscreen.drain().collect();
+        for i in 0..self.written.len() {
+            let cell = self.pending(i);
+            // Wide cells bring their continuation back with them.
+            if !cell.is_continuation() && *cell != Cell::default() {
+                let position = Position::new(i as i32 % self.cols, i as i32 / self.cols);
+                content.push((position, cell.clone()));
+            }
+        }
+        content.sort_unstable_by_key(|(position, _)| *position);
+
+        *self = Self::with_size(col_row);
+        for (position, cell) in content {
+            let position = position + offset;
+            if self.index(&position).is_some() {
+                self.write_cell(&position, cell);
+            } else if policy != Resize::Crop {
+                self.offscreen.insert(position, cell);
+            }
+        }
+        self.redraw();
+    }
+
+    /**
+     * Makes the next [#flush] return every visible cell, whether it changed or not.
+     */
+    pub fn redraw(&mut self) {
+        self.touch_all();
+        self.redraw = true;
+    }
+
     /**
      * Inserts the character into the buffer. Until the screen is [#flush]-ed the written
      * value will not be returned by [#read]. Returns the character that was previously
      * buffered, or [BLANK].
      */
     pub fn write(&mut self, position: &Position, ch: char) -> char {
-        self.buffer.insert(*position, ch).unwrap_or(BLANK)
+        self.write_cell(position, Cell::new(ch)).ch()
+    }
+
+    /**
+     * Like [#write], but with the style the character should be drawn in.
+     */
+    pub fn write_styled(&mut self, position: &Position, ch: char, style: Style) -> char {
+        self.write_cell(position, Cell::styled(ch, style)).ch()
+    }
+
+    /**
+     * Writes a single grapheme cluster, returning the number of columns it takes up.
+     */
+    pub fn write_grapheme(&mut self, position: &Position, grapheme: &str, style: Style) -> i32 {
+        let cell = Cell::with_grapheme(grapheme, style);
+        let width = cell.width() as i32;
+        self.write_cell(position, cell);
+        width
+    }
+
+    /**
+     * Inserts the cell into the buffer, returning the cell that was previously buffered or a
+     * blank one.
+     *
+     * A wide cell also takes the next column with a continuation. Wide graphemes are never
+     * left half drawn: any that this write overlaps are blanked, and one that doesn't fit
+     * before the last column is replaced by a blank.
+     */
+    pub fn write_cell(&mut self, position: &Position, cell: Cell) -> Cell {
+        let i = match self.index(position) {
+            Some(i) => i,
+            // Only cells kept by a resize are replaced, so drawing off the screen can't pile up.
+            None => {
+                return match self.offscreen.get_mut(position) {
+                    Some(kept) => std::mem::replace(kept, cell),
+                    None => Cell::default(),
+                }
+            }
+        };
+
+        let mut cell = cell;
+        let wide = cell.width() == 2;
+        if cell.is_continuation() || (wide && position.col + 1 >= self.cols) {
+            cell = Cell::styled(BLANK, cell.style);
+        }
+
+        self.break_wide(i);
+        if cell.width() == 2 {
+            self.break_wide(i + 1);
+            let continuation = Cell {
+                grapheme: Grapheme::Continuation,
+                style: cell.style,
+            };
+            self.put(i + 1, continuation);
+        }
+        self.put(i, cell)
     }
 
     pub fn clear(&mut self) {
-        for &pos in self.written.keys() {
-            self.buffer.insert(pos, BLANK);
+        for i in 0..self.written.len() {
+            if self.buffer[i].is_some() || self.written[i] != Cell::default() {
+                self.put(i, Cell::default());
+            }
         }
+        self.offscreen.clear();
     }
 
     /**
@@ -48,39 +196,266 @@ impl Screen {
      * If the position has not been touched, [BLANK] is returned.
      */
     pub fn read(&self, position: &Position) -> char {
-        match self.written.get(position) {
-            Some(&ch) => ch,
+        match self.index(position) {
+            Some(i) => self.written[i].ch(),
             None => BLANK,
         }
     }
 
     /**
-     * Flushes the buffered writes into the written state and returns copies of the elements
-     * that were written.
+     * Returns the cell that has been written and flushed at the designated position, or a blank
+     * one.
      */
-    pub fn flush(&mut self) -> Vec<(Position, char)> {
-        let mut updates: Vec<(Position, char)> = Vec::with_capacity(self.buffer.capacity());
-        for (&position, &ch) in self.buffer.iter() {
-            if self.clamp(&position) != position {
-                // Out-of-bounds positions can be buffered, but the are ignored at flush.
-                continue;
+    pub fn read_cell(&self, position: &Position) -> Cell {
+        match self.index(position) {
+            Some(i) => self.written[i].clone(),
+            None => Cell::default(),
+        }
+    }
+
+    /**
+     * Returns the cell as it will be after the next [#flush], including buffered writes.
+     * Positions off the screen read as blank.
+     */
+    pub fn read_pending(&self, position: &Position) -> Cell {
+        match self.index(position) {
+            Some(i) => self.pending(i).clone(),
+            None => Cell::default(),
+        }
+    }
+
+    /**
+     * Iterates over the written and flushed cells in row-major order.
+     */
+    pub fn cells(&self) -> impl Iterator<Item = (Position, &Cell)> {
+        let cols = self.cols;
+        self.written
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (Position::new(i as i32 % cols, i as i32 / cols), cell))
+    }
+
+    /**
+     * Iterates over the cells that a resize pushed off the screen and keeps for when it grows
+     * back, in no particular order.
+     */
+    pub fn offscreen_cells(&self) -> impl Iterator<Item = (Position, &Cell)> {
+        self.offscreen
+            .iter()
+            .map(|(position, cell)| (*position, cell))
+    }
+
+    /**
+     * Flushes the buffered writes into the written state and returns copies of the cells that
+     * changed, in row-major order. A cell changes if either its character or its style does,
+     * and every cell counts as changed after a [#redraw].
+     * Continuations are included, but a wide cell's grapheme covers them when printed.
+     */
+    pub fn flush(&mut self) -> Vec<(Position, Cell)> {
+        let mut updates: Vec<(Position, Cell)> = Vec::with_capacity(self.dirty.len());
+        self.dirty.sort_unstable();
+        for i in self.dirty.drain(..) {
+            if let Some(cell) = self.buffer[i].take() {
+                if self.redraw || self.written[i] != cell {
+                    let position = Position::new(i as i32 % self.cols, i as i32 / self.cols);
+                    updates.push((position, cell.clone()));
+                    self.written[i] = cell;
+                }
             }
+        }
+        self.redraw = false;
+        updates
+    }
+
+    /**
+     * Like [#flush], but with the changed cells coalesced into runs so each can be printed after
+     * a single move. With `scroll`, a shift of whole rows is detected and returned first, and the
+     * runs are what is left to draw after the terminal has scrolled. Only scroll when the screen
+     * covers the whole terminal, since a terminal scrolls all of its rows.
+     */
+    pub fn flush_runs(&mut self, scroll: bool) -> Vec<Update> {
+        let mut updates: Vec<Update> = Vec::new();
+        let shift = if scroll { self.detect_scroll() } else { None };
+        if let Some(rows) = shift {
+            self.touch_all();
+            self.shift_written(rows);
+            updates.push(Update::Scroll(rows));
+        }
 
-            let original = self.written.insert(position, ch);
-            if original == None || original != Some(ch) {
-                updates.push((position, ch));
+        for (position, cell) in self.flush() {
+            match updates.last_mut() {
+                Some(Update::Run(start, run))
+                    if start.row == position.row
+                        && start.col + run.len() as i32 == position.col =>
+                {
+                    run.push(cell)
+                }
+                _ => updates.push(Update::Run(position, vec![cell])),
             }
         }
         updates
     }
 
+    /**
+     * Moves all the visible content up by the number of rows, or down if negative, leaving blank
+     * rows behind. Off-screen writes stay where they are.
+     */
+    pub fn scroll(&mut self, rows: i32) {
+        let cells: Vec<Cell> = (0..self.written.len())
+            .map(|i| self.pending(i).clone())
+            .collect();
+        for i in 0..cells.len() {
+            let source = i as i32 + rows * self.cols;
+            let cell = if 0 <= source && (source as usize) < cells.len() {
+                cells[source as usize].clone()
+            } else {
+                Cell::default()
+            };
+            if *self.pending(i) != cell {
+                self.put(i, cell);
+            }
+        }
+    }
+
     // pub(crate) fn mem(&self) -> usize {
-    //     (std::mem::size_of::<Position>() + std::mem::size_of::<char>()) * self.written.len()
+    //     std::mem::size_of::<Cell>() * (self.written.len() + self.buffer.len())
     // }
 
+    /**
+     * The area of the screen, from the top left.
+     */
+    pub fn bounds(&self) -> Rect {
+        Rect::new(Position::new(0, 0), Position::new(self.cols, self.rows))
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.bounds().contains(position)
+    }
+
     pub fn clamp(&self, position: &Position) -> Position {
-        position.clamp(Position::new(0, 0), Position::new(self.cols, self.rows))
+        self.bounds().clamp(position)
     }
+
+    /**
+     * The cell as it will be after the next flush.
+     */
+    fn pending(&self, i: usize) -> &Cell {
+        self.buffer[i].as_ref().unwrap_or(&self.written[i])
+    }
+
+    /**
+     * The number of rows the content was shifted by since the last flush, if scrolling the
+     * terminal by it would leave enough fewer rows to redraw. Only tried when at least a couple
+     * of rows' worth of cells were written.
+     */
+    fn detect_scroll(&self) -> Option<i32> {
+        if self.rows < 3 || self.dirty.len() < 2 * self.cols as usize {
+            return None;
+        }
+        let written = self.row_hashes(|i| &self.written[i]);
+        let pending = self.row_hashes(|i| self.pending(i));
+        let default = Cell::default();
+        let blank = hash_row(std::iter::repeat_n(&default, self.cols as usize));
+
+        let rows = self.rows;
+        let matching = |shift: i32| -> usize {
+            (0..rows)
+                .filter(|&row| {
+                    let source = row + shift;
+                    let scrolled = if 0 <= source && source < rows {
+                        written[source as usize]
+                    } else {
+                        blank
+                    };
+                    pending[row as usize] == scrolled
+                })
+                .count()
+        };
+
+        let unchanged = matching(0);
+        (1..rows)
+            .flat_map(|shift| vec![shift, -shift])
+            .map(|shift| (shift, matching(shift)))
+            .filter(|&(_, matched)| matched >= unchanged + MIN_SCROLL_GAIN)
+            .max_by_key(|&(shift, matched)| (matched, -shift.abs()))
+            .map(|(shift, _)| shift)
+    }
+
+    fn row_hashes<'c>(&self, cell: impl Fn(usize) -> &'c Cell) -> Vec<u64> {
+        let cols = self.cols as usize;
+        (0..self.rows as usize)
+            .map(|row| hash_row((0..cols).map(|col| cell(row * cols + col))))
+            .collect()
+    }
+
+    /**
+     * Shifts the written state the way the terminal's content moves when it scrolls.
+     */
+    fn shift_written(&mut self, rows: i32) {
+        let cells = (rows.unsigned_abs() as usize * self.cols as usize).min(self.written.len());
+        let len = self.written.len();
+        if rows > 0 {
+            self.written.rotate_left(cells);
+            self.written[len - cells..].fill(Cell::default());
+        } else {
+            self.written.rotate_right(cells);
+            self.written[..cells].fill(Cell::default());
+        }
+    }
+
+    /**
+     * Marks every cell as written to, keeping what it will show after the next flush.
+     */
+    fn touch_all(&mut self) {
+        for i in 0..self.written.len() {
+            if self.buffer[i].is_none() {
+                self.put(i, self.written[i].clone());
+            }
+        }
+    }
+
+    fn put(&mut self, i: usize, cell: Cell) -> Cell {
+        match self.buffer[i].replace(cell) {
+            Some(previous) => previous,
+            None => {
+                self.dirty.push(i);
+                self.written[i].clone()
+            }
+        }
+    }
+
+    /**
+     * Blanks the other half of a wide grapheme at the index, if there is one, before the index
+     * is overwritten.
+     */
+    fn break_wide(&mut self, i: usize) {
+        let col = i as i32 % self.cols;
+        let pending = self.pending(i);
+        if pending.is_continuation() && col > 0 {
+            let style = self.pending(i - 1).style;
+            self.put(i - 1, Cell::styled(BLANK, style));
+        } else if pending.width() == 2 && col + 1 < self.cols {
+            let style = pending.style;
+            self.put(i + 1, Cell::styled(BLANK, style));
+        }
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        let Position { col, row } = *position;
+        if 0 <= col && col < self.cols && 0 <= row && row < self.rows {
+            Some((row * self.cols + col) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+fn hash_row<'c>(cells: impl Iterator<Item = &'c Cell>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for cell in cells {
+        cell.hash(&mut hasher);
+    }
+    hasher.finish()
 }
 
 #[macro_export]
@@ -110,3 +485,186 @@ fn test_buffer() {
     assert_eq!(screen.read(&(0, 1).into()), 'h');
     assert_eq!(screen.read(&(1, 2).into()), 'i');
 }
+
+#[test]
+fn test_styled_flush() {
+    use crossterm::style::Color;
+
+    let mut screen = Screen::default();
+    let red = Style::default().fg(Color::Red);
+    screen.write_styled(&(0, 0).into(), 'a', red);
+    screen.write(&(1, 0).into(), 'b');
+    assert_eq!(screen.flush().len(), 2);
+
+    // Same character, different style.
+    screen.write(&(0, 0).into(), 'a');
+    // Same cell entirely.
+    screen.write(&(1, 0).into(), 'b');
+    let out = screen.flush();
+    assert_eq!(out, vec![((0, 0).into(), Cell::new('a'))]);
+    assert_eq!(screen.read_cell(&(0, 0).into()).style, Style::default());
+}
+
+#[test]
+fn test_bounds() {
+    let mut screen = Screen::with_size((3, 2).into());
+    scrite!(
+        &mut screen,
+        (2, 1, 'a'),
+        (3, 1, 'b'),
+        (-1, 0, 'c'),
+        (0, 2, 'd')
+    );
+    assert_eq!(screen.flush(), vec![((2, 1).into(), Cell::new('a'))]);
+    assert_eq!(screen.read(&(3, 1).into()), BLANK);
+
+    // Off-screen writes are dropped.
+    assert_eq!(screen.write(&(3, 1).into(), 'e'), BLANK);
+    assert_eq!(screen.write(&(2, 1).into(), 'f'), 'a');
+    assert_eq!(screen.offscreen_cells().count(), 0);
+
+    screen.clear();
+    assert_eq!(screen.flush(), vec![((2, 1).into(), Cell::default())]);
+    assert!(screen.flush().is_empty());
+}
+
+#[test]
+fn test_wide() {
+    let plain = Style::default();
+    let mut screen = Screen::with_size((4, 1).into());
+    assert_eq!(screen.write_grapheme(&(0, 0).into(), "漢", plain), 2);
+    assert_eq!(screen.write_grapheme(&(3, 0).into(), "👪", plain), 2);
+    let out = screen.flush();
+    assert_eq!(
+        out,
+        vec![
+            ((0, 0).into(), Cell::with_grapheme("漢", plain)),
+            (
+                (1, 0).into(),
+                Cell {
+                    grapheme: Grapheme::Continuation,
+                    style: plain,
+                }
+            ),
+        ]
+    );
+    // The emoji didn't fit in the last column.
+    assert_eq!(screen.read(&(3, 0).into()), BLANK);
+
+    // Overwriting the continuation blanks the rest of the glyph.
+    screen.write(&(1, 0).into(), 'x');
+    let out = screen.flush();
+    assert_eq!(
+        out,
+        vec![
+            ((0, 0).into(), Cell::new(BLANK)),
+            ((1, 0).into(), Cell::new('x'))
+        ]
+    );
+
+    // A wide glyph overlapping another one's leading half blanks its continuation.
+    screen.write_grapheme(&(1, 0).into(), "漢", plain);
+    screen.flush();
+    screen.write_grapheme(&(2, 0).into(), "字", plain);
+    let out = screen.flush();
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[0], ((1, 0).into(), Cell::new(BLANK)));
+    assert_eq!(
+        screen.read_cell(&(2, 0).into()),
+        Cell::with_grapheme("字", plain)
+    );
+    assert!(screen.read_cell(&(3, 0).into()).is_continuation());
+}
+
+#[test]
+fn test_resize() {
+    let plain = Style::default();
+    let mut screen = Screen::with_size((4, 2).into());
+    scrite!(&mut screen, (0, 0, 'a'), (3, 1, 'b'));
+    screen.flush();
+
+    screen.resize((2, 2).into(), Resize::KeepTopLeft);
+    assert_eq!((screen.cols, screen.rows), (2, 2));
+    // The whole visible area is redrawn.
+    let out = screen.flush();
+    assert_eq!(out.len(), 4);
+    assert_eq!(out[0], ((0, 0).into(), Cell::new('a')));
+    assert!(screen.flush().is_empty());
+    // Writing over a kept cell replaces it.
+    assert_eq!(screen.write(&(3, 1).into(), 'c'), 'b');
+    assert_eq!(screen.offscreen_cells().count(), 1);
+
+    // Growing back brings the kept cell into view.
+    screen.resize((4, 2).into(), Resize::KeepTopLeft);
+    screen.flush();
+    assert_eq!(screen.read(&(3, 1).into()), 'c');
+
+    screen.resize((2, 2).into(), Resize::Crop);
+    screen.resize((4, 2).into(), Resize::Crop);
+    screen.flush();
+    assert_eq!(screen.read(&(0, 0).into()), 'a');
+    assert_eq!(screen.read(&(3, 1).into()), BLANK);
+
+    // Pending writes and wide glyphs move with the content.
+    screen.write_grapheme(&(1, 1).into(), "漢", plain);
+    screen.resize((6, 4).into(), Resize::Recenter);
+    screen.flush();
+    assert_eq!(screen.read(&(1, 1).into()), 'a');
+    assert_eq!(
+        screen.read_cell(&(2, 2).into()),
+        Cell::with_grapheme("漢", plain)
+    );
+    assert!(screen.read_cell(&(3, 2).into()).is_continuation());
+
+    screen.resize((4, 2).into(), Resize::Recenter);
+    screen.flush();
+    assert_eq!(screen.read(&(0, 0).into()), 'a');
+}
+
+#[test]
+fn test_flush_runs() {
+    let mut screen = Screen::with_size((4, 4).into());
+    scrite!(
+        &mut screen,
+        (1, 0, 'a'),
+        (2, 0, 'b'),
+        (0, 1, 'c'),
+        (3, 1, 'd')
+    );
+    let runs = screen.flush_runs(true);
+    assert_eq!(
+        runs,
+        vec![
+            Update::Run((1, 0).into(), vec![Cell::new('a'), Cell::new('b')]),
+            Update::Run((0, 1).into(), vec![Cell::new('c')]),
+            Update::Run((3, 1).into(), vec![Cell::new('d')]),
+        ]
+    );
+
+    for row in 0..4 {
+        for col in 0..4 {
+            screen.write(&(col, row).into(), (b'a' + (row * 4 + col) as u8) as char);
+        }
+    }
+    screen.flush();
+
+    // Scrolling up by one only leaves the new bottom row to draw.
+    screen.scroll(1);
+    scrite!(&mut screen, (0, 3, 'w'), (1, 3, 'x'));
+    let runs = screen.flush_runs(true);
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0], Update::Scroll(1));
+    assert_eq!(
+        runs[1],
+        Update::Run((0, 3).into(), vec![Cell::new('w'), Cell::new('x')])
+    );
+    assert_eq!(screen.read(&(0, 0).into()), 'e');
+    assert_eq!(screen.read(&(3, 2).into()), 'p');
+
+    // Without scrolling, every row is redrawn.
+    screen.scroll(-2);
+    let runs = screen.flush_runs(false);
+    assert_eq!(runs.len(), 4);
+    assert_eq!(screen.read(&(0, 2).into()), 'e');
+    assert_eq!(screen.read(&(0, 0).into()), BLANK);
+}
@@ -0,0 +1,175 @@
+use std::env;
+use std::path::Path;
+
+use crate::position::Position;
+use crate::save::{load, load_file, save_file, to_text, Format};
+use crate::screen::Screen;
+
+/**
+ * Set to anything but `0` to have [assert_snapshot] write the snapshots instead of checking them.
+ */
+pub const UPDATE_VAR: &str = "LIIB_UPDATE_SNAPSHOTS";
+
+/**
+ * Differences listed before the rest are left out.
+ */
+const MAX_LISTED: usize = 20;
+
+/**
+ * Compares the cells of two screens, characters and styles both, and describes where they
+ * differ: the two side by side with the rows that differ marked, then the differing cells.
+ * Positions outside a screen count as blank, so sizes only matter where there's content.
+ */
+pub fn diff(actual: &Screen, expected: &Screen) -> Option<String> {
+    let cols = actual.cols.max(expected.cols);
+    let rows = actual.rows.max(expected.rows);
+
+    let mut differences: Vec<String> = Vec::new();
+    let mut changed_rows: Vec<bool> = vec![false; rows.max(0) as usize];
+    for row in 0..rows {
+        for col in 0..cols {
+            let position = Position::new(col, row);
+            let (found, wanted) = (actual.read_cell(&position), expected.read_cell(&position));
+            if found == wanted {
+                continue;
+            }
+            changed_rows[row as usize] = true;
+            differences.push(if found.grapheme == wanted.grapheme {
+                format!(
+                    "{}: expected {:?}, found {:?}",
+                    position, wanted.style, found.style
+                )
+            } else {
+                format!(
+                    "{}: expected '{}', found '{}'",
+                    position, wanted.grapheme, found.grapheme
+                )
+            });
+        }
+    }
+    if differences.is_empty() {
+        return None;
+    }
+
+    let width = cols.max("expected".len() as i32) as usize;
+    let expected_lines = lines(expected, rows);
+    let actual_lines = lines(actual, rows);
+    let mut out = format!("    {:<width$} | actual\n", "expected", width = width);
+    for (row, changed) in changed_rows.iter().enumerate() {
+        out.push_str(&format!(
+            "{:>3} {:<width$} | {}{}\n",
+            row,
+            expected_lines[row],
+            actual_lines[row],
+            if *changed { "  <" } else { "" },
+            width = width
+        ));
+    }
+    out.push_str(&format!("{} cells differ:\n", differences.len()));
+    for difference in differences.iter().take(MAX_LISTED) {
+        out.push_str(&format!("  {}\n", difference));
+    }
+    if differences.len() > MAX_LISTED {
+        out.push_str("  ...\n");
+    }
+    Some(out)
+}
+
+/**
+ * Asserts that the flushed state of the screen is the expected text. The text is read like a
+ * saved screen, so it may contain ANSI escapes to check styles too.
+ */
+pub fn assert_screen(screen: &Screen, expected: &str) {
+    let mut expected = load(expected);
+    expected.flush();
+    if let Some(diff) = diff(screen, &expected) {
+        panic!("screen does not match:\n{}", diff);
+    }
+}
+
+/**
+ * Asserts that the flushed state of the screen matches the snapshot file, saved as text or ANSI
+ * depending on its extension (see [Format::for_path]). When the [UPDATE_VAR] environment
+ * variable is set the snapshot is written instead.
+ */
+pub fn assert_snapshot(screen: &Screen, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    if env::var(UPDATE_VAR).is_ok_and(|v| v != "0") {
+        save_file(screen, path, Format::for_path(path)).unwrap();
+        return;
+    }
+
+    let mut expected = match load_file(path) {
+        Ok(screen) => screen,
+        Err(e) => panic!(
+            "could not read snapshot {}: {}\nRun with {}=1 to create it.",
+            path.display(),
+            e,
+            UPDATE_VAR
+        ),
+    };
+    expected.flush();
+    if let Some(diff) = diff(screen, &expected) {
+        panic!(
+            "screen does not match snapshot {}:\n{}Run with {}=1 to update it.",
+            path.display(),
+            diff,
+            UPDATE_VAR
+        );
+    }
+}
+
+fn lines(screen: &Screen, rows: i32) -> Vec<String> {
+    let text = to_text(screen);
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    lines.resize(rows.max(0) as usize, String::new());
+    lines
+}
+
+#[test]
+fn test_diff() {
+    use crate::cell::Style;
+    use crossterm::style::Color;
+
+    let mut screen = Screen::with_size((6, 3).into());
+    crate::scrite!(&mut screen, (0, 0, 'h'), (1, 0, 'i'), (2, 2, 'x'));
+    screen.write_styled(&(3, 2).into(), 'y', Style::default().fg(Color::Red));
+    screen.flush();
+
+    assert_screen(&screen, "hi\n\n  x\x1b[38;5;9my");
+    assert_snapshot(
+        &screen,
+        concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/diff.ans"),
+    );
+
+    let mut expected = load("ha\n\n  xy\n   z");
+    expected.flush();
+    let diff = diff(&screen, &expected).unwrap();
+    assert_eq!(
+        diff,
+        [
+            "    expected | actual",
+            "  0 ha       | hi  <",
+            "  1          | ",
+            "  2   xy     |   xy  <",
+            "  3    z     |   <",
+            "3 cells differ:",
+            "  (1, 0): expected 'a', found 'i'",
+            "  (3, 2): expected Style { fg: Reset, bg: Reset, bold: false, underline: false, \
+             reverse: false }, found Style { fg: Red, bg: Reset, bold: false, underline: false, \
+             reverse: false }",
+            "  (3, 3): expected 'z', found ' '",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+#[should_panic(expected = "(1, 0): expected 'a', found 'i'")]
+fn test_assert_screen() {
+    let mut screen = Screen::with_size((2, 1).into());
+    crate::scrite!(&mut screen, (0, 0, 'h'), (1, 0, 'i'));
+    screen.flush();
+    assert_screen(&screen, "ha");
+}
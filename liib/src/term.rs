@@ -5,11 +5,15 @@
 use core::time::Duration;
 use crossterm::{
     cursor::{
-        position as crossterm_position, MoveTo, MoveToNextLine, RestorePosition, SavePosition,
+        position as crossterm_position, Hide, MoveTo, MoveToNextLine, RestorePosition,
+        SavePosition, Show,
+    },
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
+        KeyModifiers, MouseButton, MouseEvent,
     },
-    event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers},
     execute, queue,
-    style::{Color, Print, SetForegroundColor},
+    style::{Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
     terminal::{
         disable_raw_mode,
         enable_raw_mode,
@@ -17,6 +21,8 @@ use crossterm::{
         //
         Clear,
         ClearType,
+        EnterAlternateScreen,
+        LeaveAlternateScreen,
         //
         ScrollDown,
         ScrollUp,
@@ -24,17 +30,30 @@ use crossterm::{
 };
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::env;
+use std::fs::File;
 use std::io::*;
+use std::panic;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
 use std::thread::sleep;
+use std::time::Instant;
 
-use crate::position::{Position, Visible};
+use crate::app::{App, AppLoop, Flow};
+use crate::backend::{Backend, CrosstermBackend};
+use crate::cast::Recorder;
+use crate::cell::{Cell, Style};
+use crate::draw::{line, write_str, Wrap};
+use crate::keymap::{Action, Keymap};
+use crate::position::Position;
 use crate::ringer::Ringer;
-use crate::screen::Screen;
+use crate::screen::{Resize, Screen, Update};
+use crate::widget::{StatusBar, Widget};
 
 macro_rules! ex {
     ( $( $x:expr ),* ) => {
         execute!(
-            stdout(),
+            terminal_out(),
             $(
                 $x,
             )*
@@ -46,7 +65,7 @@ macro_rules! ex {
 macro_rules! rex {
     ( $( $x:expr ),* ) => {
         execute!(
-            stdout(),
+            terminal_out(),
             SavePosition,
             $(
                 $x,
@@ -59,11 +78,233 @@ macro_rules! rex {
 
 const BLANK: char = ' ';
 
-pub fn free_draw() -> crossterm::Result<()> {
-    make_room();
-    edit_loop()?;
+const RAW: u8 = 1;
+const ALTERNATE_SCREEN: u8 = 1 << 1;
+const HIDDEN_CURSOR: u8 = 1 << 2;
+const MOUSE: u8 = 1 << 3;
 
-    Ok(())
+/**
+ * The terminal modes some [SessionGuard] has entered and not yet left.
+ */
+static ENTERED: AtomicU8 = AtomicU8::new(0);
+static PANIC_HOOK: Once = Once::new();
+
+/**
+ * The terminal modes to draw in: by default raw mode, the alternate screen and a hidden cursor.
+ * Starting it returns a guard that leaves them again when dropped, or when the program panics.
+ *
+ * Sessions nest: modes an outer session already entered are left alone by the inner one, so
+ * functions like [dump_screen] can start their own to work standalone and change nothing when
+ * called inside a bigger one.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Session {
+    modes: u8,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            modes: RAW | ALTERNATE_SCREEN | HIDDEN_CURSOR,
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Only raw mode, drawing over whatever the terminal shows.
+     */
+    pub fn raw() -> Self {
+        Self { modes: RAW }
+    }
+
+    /**
+     * Keeps the cursor visible, for editing.
+     */
+    pub fn show_cursor(self) -> Self {
+        Self {
+            modes: self.modes & !HIDDEN_CURSOR,
+        }
+    }
+
+    /**
+     * Captures the mouse, so its events can be read.
+     */
+    pub fn mouse(self) -> Self {
+        Self {
+            modes: self.modes | MOUSE,
+        }
+    }
+
+    /**
+     * Enters the modes that aren't entered yet.
+     */
+    pub fn start(self) -> crossterm::Result<SessionGuard> {
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                // Otherwise the message is lost on the alternate screen.
+                leave(u8::MAX);
+                previous(info);
+            }));
+        });
+
+        let modes = self.modes & !ENTERED.fetch_or(self.modes, Ordering::SeqCst);
+        let guard = SessionGuard { modes };
+        if modes & RAW != 0 {
+            enable_raw_mode()?;
+        }
+        let mut out = terminal_out();
+        if modes & ALTERNATE_SCREEN != 0 {
+            queue!(out, EnterAlternateScreen)?;
+        }
+        if modes & HIDDEN_CURSOR != 0 {
+            queue!(out, Hide)?;
+        }
+        if modes & MOUSE != 0 {
+            queue!(out, EnableMouseCapture)?;
+        }
+        out.flush()?;
+        Ok(guard)
+    }
+}
+
+/**
+ * Leaves the modes its [Session] entered when dropped.
+ */
+#[derive(Debug)]
+pub struct SessionGuard {
+    modes: u8,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        leave(self.modes);
+    }
+}
+
+/**
+ * Leaves those of the modes that are entered, in the reverse order. Errors are ignored, there is
+ * nothing better to do with the terminal.
+ */
+fn leave(modes: u8) {
+    let modes = modes & ENTERED.fetch_and(!modes, Ordering::SeqCst);
+    let mut out = terminal_out();
+    if modes & MOUSE != 0 {
+        queue!(out, DisableMouseCapture).ok();
+    }
+    if modes & HIDDEN_CURSOR != 0 {
+        queue!(out, Show).ok();
+    }
+    if modes & ALTERNATE_SCREEN != 0 {
+        queue!(out, LeaveAlternateScreen).ok();
+    }
+    out.flush().ok();
+    if modes & RAW != 0 {
+        disable_raw_mode().ok();
+    }
+}
+
+/**
+ * The recording [TerminalOut] copies to, while a [RecordingGuard] is alive.
+ */
+static RECORDING: Mutex<Option<Recorder<BufWriter<File>>>> = Mutex::new(None);
+
+/**
+ * Held by the tests that record, or resize in the editor, which is recorded too, so none of them
+ * sees another's recording.
+ */
+#[cfg(test)]
+static RECORDING_TESTS: Mutex<()> = Mutex::new(());
+
+fn recording() -> MutexGuard<'static, Option<Recorder<BufWriter<File>>>> {
+    // A panic while writing leaves the recorder as usable as anything else.
+    RECORDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/**
+ * Standard output, copied to the recording if one was started with [record]. Everything this
+ * module draws goes through it.
+ */
+#[derive(Debug)]
+pub struct TerminalOut {
+    out: Stdout,
+}
+
+pub fn terminal_out() -> TerminalOut {
+    TerminalOut { out: stdout() }
+}
+
+impl Write for TerminalOut {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.out.write(buf)?;
+        if let Some(recorder) = recording().as_mut() {
+            recorder.write_all(&buf[..written])?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        if let Some(recorder) = recording().as_mut() {
+            recorder.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Starts recording what is drawn to the terminal, and its resizes, to an asciicast file that
+ * [crate::cast::Player] can replay. It stops when the guard is dropped.
+ */
+pub fn record(path: impl AsRef<std::path::Path>) -> Result<RecordingGuard> {
+    let file = BufWriter::new(File::create(path)?);
+    *recording() = Some(Recorder::new(file, get_size())?);
+    Ok(RecordingGuard { _private: () })
+}
+
+/**
+ * Adds the terminal's new size to the recording, if there is one, so it is replayed at the right
+ * size. Anything that handles resizes itself instead of with [resize_screen] should call it.
+ */
+pub fn record_resize(size: Position) {
+    if let Some(recorder) = recording().as_mut() {
+        recorder.resize(size).ok();
+    }
+}
+
+/**
+ * Stops the recording [record] started when dropped.
+ */
+#[derive(Debug)]
+pub struct RecordingGuard {
+    _private: (),
+}
+
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        if let Some(mut recorder) = recording().take() {
+            recorder.flush().ok();
+        }
+    }
+}
+
+/**
+ * Lets the user draw on the screen, starting from its content, until they quit, with the keys
+ * bound by the keymap. Returns the screen as it was drawn.
+ */
+pub fn free_draw(mut screen: Screen, keymap: &mut Keymap) -> crossterm::Result<Screen> {
+    let _session = Session::new().show_cursor().mouse().start()?;
+    make_room();
+    let mut backend = CrosstermBackend::stdout();
+    screen.resize(backend.size(), Resize::KeepTopLeft);
+    let mut editor = Editor::new(keymap);
+    AppLoop::new(30.0).run(&mut editor, &mut screen, &mut backend, terminal_input())?;
+    Ok(screen)
 }
 
 pub fn make_room() {
@@ -78,63 +319,401 @@ pub fn get_position() -> Position {
     crossterm_position().unwrap_or((0, 0)).into()
 }
 
-fn edit_loop() -> crossterm::Result<()> {
-    let mut screen = Screen::with_size(get_size());
-    let mut res: Res = Res::None;
+/**
+ * What the user did, as read by an [InputReader]. Mouse positions are screen positions.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Key(KeyEvent),
+    /// A mouse button went down.
+    Press(MouseButton, Position, KeyModifiers),
+    /// The mouse moved with a button held down.
+    Drag(MouseButton, Position, KeyModifiers),
+    Release(MouseButton, Position, KeyModifiers),
+    /// The wheel turned by the number of rows, positive towards the user.
+    Scroll(i32, Position, KeyModifiers),
+    /// Text that arrived all at once instead of being typed. Fast typing and key repeat can look
+    /// the same, so apps with key bindings should still look its keys up.
+    Paste(String),
+    /// The terminal's new size.
+    Resize(Position),
+}
+
+impl From<Event> for Input {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Key(key) => Input::Key(key),
+            Event::Mouse(mouse) => match mouse {
+                MouseEvent::Down(button, col, row, m) => Input::Press(button, (col, row).into(), m),
+                MouseEvent::Drag(button, col, row, m) => Input::Drag(button, (col, row).into(), m),
+                MouseEvent::Up(button, col, row, m) => Input::Release(button, (col, row).into(), m),
+                MouseEvent::ScrollDown(col, row, m) => Input::Scroll(1, (col, row).into(), m),
+                MouseEvent::ScrollUp(col, row, m) => Input::Scroll(-1, (col, row).into(), m),
+            },
+            Event::Resize(cols, rows) => Input::Resize((cols, rows).into()),
+        }
+    }
+}
 
-    enable_raw_mode()?;
-    loop {
-        let size = get_size();
-        let cursor = get_position();
-        just_dump_screen(&mut screen)?;
-        rex!(
-            MoveTo(0, 0),
-            Clear(ClearType::CurrentLine),
-            SetForegroundColor(Color::Red),
-            Print(format!(
-                "Size: {} | Pos: {} | Cur: '{}' | Res: {:?}",
-                size,
-                cursor,
-                screen.read(&cursor),
-                res
-            ))
-        );
+/**
+ * Reads terminal events as [Input]. Mouse events only arrive once mouse capture is enabled.
+ *
+ * crossterm doesn't report the markers of bracketed paste, so a paste is recognised by its
+ * characters arriving together: keys typed one by one are read as they come, while characters
+ * that are already waiting behind the first are read with it as one [Input::Paste].
+ */
+pub struct InputReader<R, P>
+where
+    R: FnMut() -> crossterm::Result<Event>,
+    P: FnMut() -> crossterm::Result<bool>,
+{
+    read: R,
+    ready: P,
+    held: Option<Event>,
+}
 
-        while !poll(Duration::from_millis(500))? {}
+type TerminalInput = InputReader<fn() -> crossterm::Result<Event>, fn() -> crossterm::Result<bool>>;
 
-        if let Event::Key(event) = read()? {
-            rex!(
-                MoveTo(0, 1),
-                Clear(ClearType::CurrentLine),
-                SetForegroundColor(Color::Blue),
-                Print(format!("{:?}", event))
-            );
+impl TerminalInput {
+    pub fn terminal() -> Self {
+        fn ready() -> crossterm::Result<bool> {
+            poll(Duration::from_millis(0))
+        }
+        InputReader::new(read, ready)
+    }
+}
+
+impl<R, P> InputReader<R, P>
+where
+    R: FnMut() -> crossterm::Result<Event>,
+    P: FnMut() -> crossterm::Result<bool>,
+{
+    /**
+     * Reads events with `read`, which may block, while `ready` tells whether one is waiting.
+     */
+    pub fn new(read: R, ready: P) -> Self {
+        Self {
+            read,
+            ready,
+            held: None,
+        }
+    }
 
-            let result: Res = process_event(&mut screen, event, size, cursor);
-            res = result;
+    /**
+     * Waits for the next input.
+     */
+    pub fn read(&mut self) -> crossterm::Result<Input> {
+        let event = match self.held.take() {
+            Some(event) => event,
+            None => (self.read)()?,
+        };
+        let first = match pasted_char(&event) {
+            Some(ch) => ch,
+            None => return Ok(event.into()),
+        };
 
-            match result {
-                Res::Move(dp) => {
-                    let np: Visible = (cursor + dp).into();
-                    ex!(MoveTo(np.0, np.1))
+        let mut text = first.to_string();
+        while (self.ready)()? {
+            let event = (self.read)()?;
+            match pasted_char(&event) {
+                Some(ch) => text.push(ch),
+                None => {
+                    self.held = Some(event);
+                    break;
                 }
-                Res::Write(ch) => {
-                    screen.write(&cursor, ch);
+            }
+        }
+        Ok(if text.chars().count() > 1 {
+            Input::Paste(text)
+        } else {
+            event.into()
+        })
+    }
+}
+
+/**
+ * The character a key adds to pasted text, if it could be part of one.
+ */
+fn pasted_char(event: &Event) -> Option<char> {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers,
+        }) if (*modifiers - KeyModifiers::SHIFT).is_empty() => Some(*ch),
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::NONE,
+        }) => Some('\n'),
+        _ => None,
+    }
+}
+
+/**
+ * Input from the terminal for [AppLoop::run], read with an [InputReader].
+ */
+pub fn terminal_input() -> impl FnMut(Duration) -> crossterm::Result<Option<Input>> {
+    let mut reader = InputReader::terminal();
+    move |timeout| {
+        if reader.held.is_some() || poll(timeout)? {
+            reader.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/**
+ * The free-draw editor, see [edit_loop].
+ */
+struct Editor<'k> {
+    keymap: &'k mut Keymap,
+    res: Res,
+    brush: char,
+    stroke: Option<Position>,
+    last_input: Option<Input>,
+    /// Where the cursor was last seen, until it may have moved.
+    cursor: Option<Position>,
+}
+
+impl<'k> Editor<'k> {
+    fn new(keymap: &'k mut Keymap) -> Self {
+        Self {
+            keymap,
+            res: Res::None,
+            brush: '#',
+            stroke: None,
+            last_input: None,
+            cursor: None,
+        }
+    }
+
+    /**
+     * Where the cursor is, only asking the backend when it may have moved, as that waits for
+     * the terminal to answer.
+     */
+    fn cursor(&mut self, backend: &mut impl Backend) -> crossterm::Result<Position> {
+        match self.cursor {
+            Some(cursor) => Ok(cursor),
+            None => {
+                let cursor = backend.cursor()?;
+                self.cursor = Some(cursor);
+                Ok(cursor)
+            }
+        }
+    }
+
+    /**
+     * Moves the cursor, which the backend may keep on the screen.
+     */
+    fn move_to(
+        &mut self,
+        backend: &mut impl Backend,
+        position: &Position,
+    ) -> crossterm::Result<()> {
+        self.cursor = None;
+        backend.move_to(position)?;
+        backend.flush()
+    }
+
+    /**
+     * Performs the action at the cursor.
+     */
+    fn act(
+        &mut self,
+        action: Action,
+        screen: &mut Screen,
+        backend: &mut impl Backend,
+    ) -> crossterm::Result<Flow> {
+        let cursor = self.cursor(backend)?;
+        let result = perform(screen, action, backend.size(), cursor);
+        self.res = result;
+
+        match result {
+            Res::Move(dp) => self.move_to(backend, &(cursor + dp))?,
+            Res::Write(ch) => {
+                screen.write(&cursor, ch);
+                self.brush = ch;
+            }
+            Res::Bell(count) => {
+                for _ in 0..count {
+                    backend.bell()?;
+                    backend.flush()?;
+                    sleep(Duration::from_millis(100));
                 }
-                Res::Bell(count) => {
-                    bells(count);
+            }
+            Res::Quit => return Ok(Flow::Quit),
+            Res::None => {}
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+impl<'k, B: Backend> App<B> for Editor<'k> {
+    fn input(
+        &mut self,
+        input: Input,
+        screen: &mut Screen,
+        backend: &mut B,
+    ) -> crossterm::Result<Flow> {
+        let cursor = self.cursor(backend)?;
+        self.last_input = Some(input.clone());
+        match input {
+            Input::Resize(size) => {
+                record_resize(size);
+                // The terminal keeps the cursor inside.
+                self.cursor = None;
+                backend.clear()?;
+                screen.resize(size, Resize::KeepTopLeft);
+            }
+            Input::Press(button, position, _) => {
+                paint(screen, button, &position, &position, self.brush);
+                self.stroke = Some(position);
+                self.move_to(backend, &position)?;
+            }
+            Input::Drag(button, position, _) => {
+                // Fast drags skip cells, so the stroke is joined up.
+                let from = self.stroke.unwrap_or(position);
+                paint(screen, button, &from, &position, self.brush);
+                self.stroke = Some(position);
+                self.move_to(backend, &position)?;
+            }
+            Input::Release(..) => self.stroke = None,
+            Input::Scroll(rows, _, _) => {
+                self.move_to(backend, &(cursor + Position::DOWN * rows))?
+            }
+            Input::Paste(text) => {
+                // Pastes are only told apart from fast typing by timing, so their keys still go
+                // through the keymap. Characters that write themselves are laid out from the
+                // cursor, with unbound line breaks going back to its column.
+                let mut at = cursor;
+                for ch in text.chars() {
+                    let key = match ch {
+                        '\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                        ch => KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE),
+                    };
+                    match self.keymap.feed(key) {
+                        None => {}
+                        Some(Action::Write(written)) if written == ch => {
+                            at.col += screen.write_grapheme(&at, &ch.to_string(), Style::default());
+                        }
+                        Some(Action::None) if ch == '\n' => {
+                            at = Position::new(cursor.col, at.row + 1)
+                        }
+                        Some(action) => {
+                            if self.act(action, screen, backend)? == Flow::Quit {
+                                return Ok(Flow::Quit);
+                            }
+                            at = self.cursor(backend)?;
+                        }
+                    }
+                }
+            }
+            Input::Key(event) => {
+                // Waiting for the rest of a sequence.
+                if let Some(action) = self.keymap.feed(event) {
+                    return self.act(action, screen, backend);
                 }
-                Res::Quit => break,
-                Res::None => {}
             }
         }
+        Ok(Flow::Continue)
     }
-    disable_raw_mode()?;
 
-    Ok(())
+    fn animated(&self) -> bool {
+        false
+    }
+
+    fn decorate(&mut self, screen: &Screen, backend: &mut B) -> crossterm::Result<()> {
+        let cursor = self.cursor(backend)?;
+        status_line(
+            backend,
+            0,
+            Color::Red,
+            &format!(
+                "Size: {} | Pos: {} | Cur: '{}' | Res: {:?}",
+                backend.size(),
+                cursor,
+                screen.read(&cursor),
+                self.res
+            ),
+        )?;
+        if let Some(input) = &self.last_input {
+            status_line(backend, 1, Color::Blue, &format!("{:?}", input))?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Runs the editor on the backend, taking input from `next_input` until the user quits. Keys do
+ * what the keymap binds them to. Dragging with the left mouse button draws with the last
+ * character typed, the right one erases.
+ */
+pub fn edit_loop(
+    mut screen: Screen,
+    backend: &mut impl Backend,
+    keymap: &mut Keymap,
+    mut next_input: impl FnMut() -> crossterm::Result<Input>,
+) -> crossterm::Result<Screen> {
+    screen.resize(backend.size(), Resize::KeepTopLeft);
+    let mut editor = Editor::new(keymap);
+    AppLoop::unlimited().run(&mut editor, &mut screen, backend, |_| {
+        next_input().map(Some)
+    })?;
+    Ok(screen)
+}
+
+/**
+ * Prints the text over a whole row, leaving the cursor where it was.
+ */
+fn status_line(
+    backend: &mut impl Backend,
+    row: i32,
+    color: Color,
+    text: &str,
+) -> crossterm::Result<()> {
+    let mut bar = StatusBar::new(text, "").style(Style::default().fg(color));
+    draw_row(backend, row, &mut bar)
+}
+
+/**
+ * Draws the widget over a whole row of the backend, leaving the cursor where it was.
+ */
+pub fn draw_row(
+    backend: &mut impl Backend,
+    row: i32,
+    widget: &mut impl Widget,
+) -> crossterm::Result<()> {
+    let mut line = Screen::with_size(Position::new(backend.size().col, 1));
+    let area = line.bounds();
+    widget.render(&mut line, &area);
+    line.flush();
+
+    backend.save_cursor()?;
+    backend.move_to(&(0, row).into())?;
+    for (_, cell) in line.cells() {
+        if cell.is_continuation() {
+            continue;
+        }
+        backend.set_style(&cell.style)?;
+        backend.print(&cell.grapheme.to_string())?;
+    }
+    backend.set_style(&Style::default())?;
+    backend.restore_cursor()?;
+    backend.flush()
+}
+
+/**
+ * Draws a line of the brush for the left button and erases for any other.
+ */
+fn paint(screen: &mut Screen, button: MouseButton, from: &Position, to: &Position, brush: char) {
+    let cell = match button {
+        MouseButton::Left => Cell::new(brush),
+        _ => Cell::default(),
+    };
+    line(screen, from, to, &cell);
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Res {
     Move(Position),
     Write(char),
@@ -143,117 +722,56 @@ pub enum Res {
     None,
 }
 
+/**
+ * What the key does in the editor with the keymap. A key that starts a longer sequence does
+ * nothing until the sequence is complete.
+ */
 pub fn process_event(
     screen: &mut Screen,
+    keymap: &mut Keymap,
     event: KeyEvent,
+    size: Position,
+    cursor: Position,
+) -> Res {
+    let action = keymap.feed(event).unwrap_or(Action::None);
+    perform(screen, action, size, cursor)
+}
+
+/**
+ * What the action does in the editor, for a screen of the size with the cursor at the position.
+ */
+pub fn perform(
+    screen: &mut Screen,
+    action: Action,
     Position { col: w, row: h }: Position,
-    Position { col: c, row: r }: Position,
+    cursor: Position,
 ) -> Res {
-    match event {
-        // Jump ends
-        KeyEvent {
-            code: KeyCode::Left,
-            modifiers: KeyModifiers::CONTROL,
-        } => Res::Move((-w, 0).into()),
-        KeyEvent {
-            code: KeyCode::Right,
-            modifiers: KeyModifiers::CONTROL,
-        } => Res::Move((w, 0).into()),
-
-        KeyEvent {
-            code: KeyCode::Up,
-            modifiers: KeyModifiers::CONTROL,
-        } => Res::Move((0, -h).into()),
-        KeyEvent {
-            code: KeyCode::Down,
-            modifiers: KeyModifiers::CONTROL,
-        } => Res::Move((0, h).into()),
-
-        // Jump boundaries
-        KeyEvent {
-            code: KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down,
-            modifiers: KeyModifiers::ALT,
-        } => {
-            let mut res = Res::Move((0, 0).into());
-            let start_is_blank = screen.read(&(c, r).into()) == BLANK;
-
-            match event.code {
-                KeyCode::Left => {
-                    for i in 0..=c {
-                        if (screen.read(&(c - i, r).into()) == BLANK) != start_is_blank {
-                            res = Res::Move((-i, 0).into());
-                            break;
-                        }
-                    }
+    let Position { col: c, row: r } = cursor;
+    match action {
+        Action::Step(direction) => Res::Move(direction),
+        Action::JumpEnd(direction) => Res::Move((direction.col * w, direction.row * h).into()),
+        Action::JumpBoundary(direction) => {
+            let start_is_blank = screen.read(&cursor) == BLANK;
+            let limit = if direction.col < 0 {
+                c
+            } else if direction.col > 0 {
+                w - c
+            } else if direction.row < 0 {
+                r
+            } else {
+                h - r
+            };
+            for i in 0..=limit {
+                if (screen.read(&(cursor + direction * i)) == BLANK) != start_is_blank {
+                    return Res::Move(direction * i);
                 }
-                KeyCode::Right => {
-                    for i in 0..=(w - c) {
-                        if (screen.read(&(c + i, r).into()) == BLANK) != start_is_blank {
-                            res = Res::Move((i, 0).into());
-                            break;
-                        }
-                    }
-                }
-                KeyCode::Up => {
-                    for i in 0..=r {
-                        if (screen.read(&(c, r - i).into()) == BLANK) != start_is_blank {
-                            res = Res::Move((0, -i).into());
-                            break;
-                        }
-                    }
-                }
-                KeyCode::Down => {
-                    for i in 0..=(h - r) {
-                        if (screen.read(&(c, r + i).into()) == BLANK) != start_is_blank {
-                            res = Res::Move((0, i).into());
-                            break;
-                        }
-                    }
-                }
-                _ => {}
             }
-            res
+            Res::Move((0, 0).into())
         }
-
-        // Jump
-        KeyEvent {
-            code: KeyCode::Left,
-            modifiers: _,
-        } => Res::Move((-1, 0).into()),
-        KeyEvent {
-            code: KeyCode::Right,
-            modifiers: _,
-        } => Res::Move((1, 0).into()),
-
-        KeyEvent {
-            code: KeyCode::Up,
-            modifiers: _,
-        } => Res::Move((0, -1).into()),
-        KeyEvent {
-            code: KeyCode::Down,
-            modifiers: _,
-        } => Res::Move((0, 1).into()),
-
-        // Quit
-        KeyEvent {
-            code: KeyCode::Char('c'),
-            modifiers: KeyModifiers::CONTROL,
-        } => Res::Quit,
-
-        // Quit
-        KeyEvent {
-            code: KeyCode::Esc,
-            modifiers: _,
-        } => Res::Bell(c.max(r) as usize),
-
-        // Write
-        KeyEvent {
-            code: KeyCode::Char(ch),
-            modifiers: _,
-        } => Res::Write(ch),
-
-        // Unhandled
-        _ => Res::None,
+        Action::Write(ch) => Res::Write(ch),
+        Action::Bells => Res::Bell(c.max(r) as usize),
+        Action::Quit => Res::Quit,
+        Action::None => Res::None,
     }
 }
 
@@ -264,39 +782,154 @@ pub fn bells(count: usize) {
     }
 }
 
+/**
+ * Draws what changed on the screen to the terminal, in raw mode if not already in a [Session].
+ */
 pub fn dump_screen(screen: &mut Screen) -> crossterm::Result<()> {
-    enable_raw_mode()?;
-    just_dump_screen(screen)?;
-    disable_raw_mode()?;
+    let _session = Session::raw().start()?;
+    just_dump_screen(screen)
+}
 
-    Ok(())
+/**
+ * Resizes the screen to the new terminal size. The terminal reflows or drops what it was
+ * showing, so it is cleared and the screen redrawn in full on the next dump.
+ */
+pub fn resize_screen(screen: &mut Screen, size: Position, policy: Resize) {
+    record_resize(size);
+    ex!(Clear(ClearType::All));
+    screen.resize(size, policy);
+}
+
+/**
+ * The terminal events that were waiting, see [take_events].
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Pending {
+    /// The latest size the terminal was resized to.
+    pub resize: Option<Position>,
+    /// The keys pressed, in order.
+    pub keys: Vec<KeyEvent>,
+}
+
+impl Pending {
+    pub fn push(&mut self, event: Event) {
+        match event {
+            Event::Resize(cols, rows) => self.resize = Some((cols, rows).into()),
+            Event::Key(key) => self.keys.push(key),
+            Event::Mouse(_) => {}
+        }
+    }
+
+    /**
+     * Whether one of the keys was a [quit_key].
+     */
+    pub fn quit(&self) -> bool {
+        self.keys.iter().any(quit_key)
+    }
+}
+
+/**
+ * Whether the key is Ctrl-C, q or Esc. Raw mode stops Ctrl-C from interrupting the program, so
+ * anything that runs for a while in a [Session] should stop on these.
+ */
+pub fn quit_key(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        KeyCode::Char('q') | KeyCode::Esc => true,
+        _ => false,
+    }
+}
+
+/**
+ * Drains the pending terminal events without blocking.
+ */
+pub fn take_events() -> crossterm::Result<Pending> {
+    let mut pending = Pending::default();
+    while poll(Duration::from_millis(0))? {
+        pending.push(read()?);
+    }
+
+    Ok(pending)
+}
+
+/**
+ * Waits for the next key press, ignoring any other events.
+ */
+pub fn read_key() -> crossterm::Result<KeyEvent> {
+    let _session = Session::raw().start()?;
+    loop {
+        if let Event::Key(event) = read()? {
+            return Ok(event);
+        }
+    }
 }
 
+/**
+ * Rings the bell if the ringer is due, in raw mode if not already in a [Session].
+ */
 pub fn dump_ringer(ringer: &mut Ringer) -> crossterm::Result<()> {
-    enable_raw_mode()?;
+    let _session = Session::raw().start()?;
     if ringer.flush() {
         ex!(Print(7 as char));
     }
-    disable_raw_mode()?;
 
     Ok(())
 }
 
 pub fn just_dump_screen(screen: &mut Screen) -> crossterm::Result<()> {
-    let mut stdout = stdout();
-    queue!(stdout, SavePosition)?;
-    for (postion, ch) in screen.flush() {
-        let clipped: Visible = screen.clamp(&postion).into();
-        queue!(stdout, MoveTo(clipped.0, clipped.1), Print(ch))?;
-    }
-    queue!(stdout, RestorePosition)?;
-    stdout.flush()?;
+    draw_screen(screen, &mut CrosstermBackend::stdout())
+}
 
-    Ok(())
+/**
+ * Flushes the screen and draws the cells that changed on the backend, leaving the cursor where
+ * it was.
+ */
+pub fn draw_screen(screen: &mut Screen, backend: &mut impl Backend) -> crossterm::Result<()> {
+    draw(screen, backend, false)
+}
+
+/**
+ * Like [draw_screen], but when the content moved by whole rows the terminal is scrolled first,
+ * so only the rows coming in are printed. Finding the shift hashes every row, so this is for
+ * screens that scroll, like logs. It only happens when the screen covers the whole backend.
+ */
+pub fn draw_screen_scrolling(
+    screen: &mut Screen,
+    backend: &mut impl Backend,
+) -> crossterm::Result<()> {
+    let scroll = backend.size() == Position::new(screen.cols, screen.rows);
+    draw(screen, backend, scroll)
+}
+
+fn draw(screen: &mut Screen, backend: &mut impl Backend, scroll: bool) -> crossterm::Result<()> {
+    backend.save_cursor()?;
+    // Whatever was printed outside the screen may have left a style behind.
+    backend.reset_style()?;
+    for update in screen.flush_runs(scroll) {
+        match update {
+            Update::Scroll(rows) => backend.scroll(rows)?,
+            Update::Run(start, cells) => {
+                backend.move_to(&screen.clamp(&start))?;
+                for cell in cells {
+                    // Printing the wide grapheme before it already filled this column.
+                    if cell.is_continuation() {
+                        continue;
+                    }
+                    backend.set_style(&cell.style)?;
+                    backend.print(&cell.grapheme.to_string())?;
+                }
+            }
+        }
+    }
+    backend.set_style(&Style::default())?;
+    backend.restore_cursor()?;
+    backend.flush()
 }
 
 #[test]
 fn test_dump() {
+    use crate::backend::MemoryBackend;
+
     let mut screen = Screen::default();
     screen.write(&(0, 0).into(), 'a');
     screen.write(&(0, 1).into(), 'b');
@@ -310,31 +943,328 @@ fn test_dump() {
     screen.write(&(5, 3).into(), 'e');
     screen.write(&(5, 4).into(), 'f');
 
-    screen.write(&(1, 2).into(), 'h');
-    screen.write(&(2, 2).into(), 'h');
-    screen.write(&(3, 2).into(), 'h');
+    let red = Style::default().fg(Color::Red).bold();
+    screen.write_styled(&(1, 2).into(), 'h', red);
+    screen.write_styled(&(2, 2).into(), 'h', red);
+    screen.write_styled(&(3, 2).into(), 'h', red);
     screen.write(&(4, 2).into(), 'h');
 
-    make_room();
-    dump_screen(&mut screen).unwrap();
-    println!();
-}
+    let mut backend = MemoryBackend::with_size((8, 6).into());
+    draw_screen(&mut screen, &mut backend).unwrap();
+    crate::snapshot::assert_screen(
+        backend.screen(),
+        "a    a\nb    b\nd\x1b[38;5;9;1mhhh\x1b[0mhd\ne    e\nf    f",
+    );
+    assert_eq!(backend.cursor().unwrap(), (0, 0).into());
 
-pub fn e() {
-    enable_raw_mode().unwrap();
+    // Only the changes are sent to the terminal.
+    screen.write(&(1, 2).into(), 'h');
+    let mut backend = CrosstermBackend::new(Vec::new());
+    draw_screen(&mut screen, &mut backend).unwrap();
+    let mut expected: Vec<u8> = Vec::new();
+    queue!(
+        expected,
+        SavePosition,
+        SetAttribute(Attribute::Reset),
+        MoveTo(1, 2),
+        Print("h"),
+        RestorePosition
+    )
+    .unwrap();
+    assert_eq!(backend.into_inner(), expected);
 }
-pub fn d() {
-    disable_raw_mode().unwrap();
+
+#[test]
+fn test_dump_colors() {
+    use crate::color::ColorSupport;
+
+    // Dumped screens are downsampled by the backend.
+    let orange = Color::Rgb {
+        r: 255,
+        g: 135,
+        b: 0,
+    };
+    let mut screen = Screen::with_size((2, 1).into());
+    screen.write_styled(&(0, 0).into(), 'o', Style::default().fg(orange));
+    let mut backend = CrosstermBackend::new(Vec::new()).colors(ColorSupport::Ansi256);
+    draw_screen(&mut screen, &mut backend).unwrap();
+    let mut expected: Vec<u8> = Vec::new();
+    queue!(
+        expected,
+        SavePosition,
+        SetAttribute(Attribute::Reset),
+        MoveTo(0, 0),
+        SetForegroundColor(Color::AnsiValue(208)),
+        Print("o"),
+        SetForegroundColor(Color::Reset),
+        RestorePosition
+    )
+    .unwrap();
+    assert_eq!(backend.into_inner(), expected);
 }
 
-#[macro_export]
-macro_rules! rawful {
-    ($t:expr) => {
-        liib::term::e();
-        let v = $t;
-        liib::term::d();
-        v
+#[test]
+fn test_scroll_output() {
+    use crate::backend::MemoryBackend;
+
+    let size: Position = (20, 10).into();
+    let write_lines = |screen: &mut Screen, first: i32| {
+        screen.clear();
+        for row in 0..size.row {
+            let n = (first + row) as u8;
+            let line = ((b'a' + n) as char).to_string().repeat(8 + n as usize % 7);
+            crate::draw::write_str(
+                screen,
+                &(0, row).into(),
+                &line,
+                Style::default(),
+                crate::draw::Wrap::Clip,
+            );
+        }
     };
+
+    let mut screen = Screen::with_size(size);
+    let mut memory = MemoryBackend::with_size(size);
+    write_lines(&mut screen, 0);
+    draw_screen(&mut screen, &mut memory).unwrap();
+
+    // Text moving up is sent as a scroll and the new lines at the bottom, when asked for.
+    write_lines(&mut screen, 3);
+    let mut scrolled = screen.clone();
+    let mut terminal = CrosstermBackend::with_size(Vec::new(), size);
+    draw_screen_scrolling(&mut scrolled, &mut terminal).unwrap();
+    let mut unscrolled = screen.clone();
+    let mut plain = CrosstermBackend::with_size(Vec::new(), size);
+    draw_screen(&mut unscrolled, &mut plain).unwrap();
+    let mut other = CrosstermBackend::with_size(Vec::new(), (80, 24).into());
+    draw_screen_scrolling(&mut screen.clone(), &mut other).unwrap();
+    let scrolled_len = terminal.into_inner().len();
+    assert!(scrolled_len * 2 < plain.into_inner().len());
+    assert!(scrolled_len * 2 < other.into_inner().len());
+
+    draw_screen_scrolling(&mut screen, &mut memory).unwrap();
+    assert!(crate::snapshot::diff(memory.screen(), &screen).is_none());
+}
+
+#[test]
+fn test_edit_loop() {
+    use crate::backend::MemoryBackend;
+
+    let _serial = RECORDING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+    let key = |code: KeyCode| Ok(Input::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    let mut events = vec![
+        key(KeyCode::Down),
+        key(KeyCode::Down),
+        key(KeyCode::Char('h')),
+        key(KeyCode::Right),
+        key(KeyCode::Char('i')),
+        Ok(Input::Resize((4, 3).into())),
+        Ok(Input::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        ))),
+    ]
+    .into_iter();
+
+    let mut backend = MemoryBackend::with_size((6, 3).into());
+    let screen = edit_loop(
+        Screen::default(),
+        &mut backend,
+        &mut Keymap::default(),
+        || events.next().unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!((screen.cols, screen.rows), (4, 3));
+    assert_eq!(screen.read(&(0, 2).into()), 'h');
+    assert_eq!(screen.read(&(1, 2).into()), 'i');
+    // The status lines are drawn over the top of the screen.
+    crate::snapshot::assert_snapshot(
+        backend.screen(),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/edit_loop.ans"),
+    );
+}
+
+#[test]
+fn test_edit_mouse() {
+    use crate::backend::MemoryBackend;
+
+    let none = KeyModifiers::NONE;
+    let mut inputs = vec![
+        Input::Key(KeyEvent::new(KeyCode::Char('x'), none)),
+        Input::Press(MouseButton::Left, (0, 2).into(), none),
+        Input::Drag(MouseButton::Left, (4, 2).into(), none),
+        Input::Release(MouseButton::Left, (4, 2).into(), none),
+        Input::Press(MouseButton::Right, (2, 2).into(), none),
+        Input::Release(MouseButton::Right, (2, 2).into(), none),
+        Input::Scroll(1, (0, 0).into(), none),
+        Input::Paste("ab\ncd".into()),
+        Input::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+    ]
+    .into_iter();
+
+    let mut backend = MemoryBackend::with_size((6, 5).into());
+    let screen = edit_loop(
+        Screen::default(),
+        &mut backend,
+        &mut Keymap::default(),
+        || Ok(inputs.next().unwrap()),
+    )
+    .unwrap();
+    assert_eq!(backend.cursor().unwrap(), (2, 3).into());
+
+    let text: Vec<String> = (2..5)
+        .map(|row| (0..6).map(|col| screen.read(&(col, row).into())).collect())
+        .collect();
+    assert_eq!(text, vec!["xx xx ", "  ab  ", "  cd  "]);
+}
+
+#[test]
+fn test_edit_paste() {
+    use crate::backend::MemoryBackend;
+
+    // Bound keys in a paste do what they are bound to, even quitting.
+    let mut keymap = Keymap::parse("j = step down\nq = quit").unwrap();
+    let none = KeyModifiers::NONE;
+    let mut inputs = vec![
+        Input::Key(KeyEvent::new(KeyCode::Down, none)),
+        Input::Paste("aj\nbqzz".into()),
+    ]
+    .into_iter();
+
+    let mut backend = MemoryBackend::with_size((4, 4).into());
+    let mut screen = edit_loop(Screen::default(), &mut backend, &mut keymap, || {
+        Ok(inputs.next().unwrap())
+    })
+    .unwrap();
+    assert_eq!(backend.cursor().unwrap(), (0, 2).into());
+    // Quitting in the middle of the paste left the rest unrendered.
+    screen.flush();
+
+    let text: Vec<String> = (1..4)
+        .map(|row| (0..2).map(|col| screen.read(&(col, row).into())).collect())
+        .collect();
+    assert_eq!(text, vec!["a ", "  ", "b "]);
+}
+
+#[test]
+fn test_process_event() {
+    let mut screen = Screen::with_size((10, 4).into());
+    let mut keymap = Keymap::default();
+    let size = (10, 4).into();
+    let cursor = (2, 1).into();
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    assert_eq!(
+        process_event(&mut screen, &mut keymap, key(KeyCode::Left), size, cursor),
+        Res::Move(Position::LEFT)
+    );
+
+    keymap.bind(
+        &[key(KeyCode::Char('g')), key(KeyCode::Char('g'))],
+        Action::Quit,
+    );
+    let g = key(KeyCode::Char('g'));
+    assert_eq!(
+        process_event(&mut screen, &mut keymap, g, size, cursor),
+        Res::None
+    );
+    assert_eq!(
+        process_event(&mut screen, &mut keymap, g, size, cursor),
+        Res::Quit
+    );
+}
+
+#[test]
+fn test_record_resize() {
+    use crate::backend::MemoryBackend;
+    use crate::cast::{Cast, Data};
+
+    let _serial = RECORDING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let path = std::env::temp_dir().join(format!("liib-resize-{}.cast", std::process::id()));
+    let recording = record(&path).unwrap();
+    let mut inputs = vec![
+        Input::Resize((7, 3).into()),
+        Input::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+    ]
+    .into_iter();
+    let mut backend = MemoryBackend::with_size((7, 3).into());
+    edit_loop(
+        Screen::default(),
+        &mut backend,
+        &mut Keymap::default(),
+        || Ok(inputs.next().unwrap()),
+    )
+    .unwrap();
+    drop(recording);
+
+    let cast = Cast::load_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(cast
+        .events
+        .iter()
+        .any(|event| event.data == Data::Resize((7, 3).into())));
+}
+
+#[test]
+fn test_input_reader() {
+    use std::collections::VecDeque;
+
+    let key = |ch: char| Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE));
+    let events: VecDeque<Event> = vec![
+        key('a'),
+        Event::Mouse(MouseEvent::ScrollUp(1, 2, KeyModifiers::NONE)),
+        // Arrived together.
+        key('h'),
+        key('i'),
+        Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+        Event::Key(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::SHIFT)),
+        Event::Resize(3, 4),
+        key('z'),
+    ]
+    .into();
+    // Asked after each character key: the ones after 'h' were already waiting.
+    let mut waiting = vec![false, true, true, true, true, false].into_iter();
+
+    let events = std::cell::RefCell::new(events);
+    let mut reader = InputReader::new(
+        || Ok(events.borrow_mut().pop_front().unwrap()),
+        || Ok(waiting.next().unwrap()),
+    );
+    assert_eq!(
+        reader.read().unwrap(),
+        Input::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE))
+    );
+    assert_eq!(
+        reader.read().unwrap(),
+        Input::Scroll(-1, (1, 2).into(), KeyModifiers::NONE)
+    );
+    assert_eq!(reader.read().unwrap(), Input::Paste("hi\n!".into()));
+    assert_eq!(reader.read().unwrap(), Input::Resize((3, 4).into()));
+    assert_eq!(
+        reader.read().unwrap(),
+        Input::Key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE))
+    );
+}
+
+#[test]
+fn test_pending() {
+    let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+    let mut pending = Pending::default();
+    pending.push(Event::Resize(3, 4));
+    pending.push(key(KeyCode::Char('c'), KeyModifiers::NONE));
+    pending.push(Event::Resize(5, 6));
+    assert_eq!(pending.resize, Some((5, 6).into()));
+    assert_eq!(pending.keys.len(), 1);
+    assert!(!pending.quit());
+
+    pending.push(key(KeyCode::Char('c'), KeyModifiers::CONTROL));
+    assert!(pending.quit());
+    for code in [KeyCode::Char('q'), KeyCode::Esc] {
+        let mut pending = Pending::default();
+        pending.push(key(code, KeyModifiers::NONE));
+        assert!(pending.quit());
+    }
 }
 
 /**
@@ -342,7 +1272,7 @@ macro_rules! rawful {
  * dlete lines from the ends. Not nearly as useful.
  */
 pub fn scroll_test() -> crossterm::Result<()> {
-    let (_w, h): Visible = get_size().into();
+    let (_w, h) = get_size().saturating_visible();
 
     ex!(Print("\n--A--\n"));
 
@@ -0,0 +1,594 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::cell::{Cell, Style};
+use crate::position::{Position, Rect};
+use crate::screen::Screen;
+use crate::term::Input;
+
+/**
+ * Something drawn into an area of a screen, like a pane solved by a [crate::layout::Layout],
+ * that may also react to input.
+ */
+pub trait Widget {
+    /**
+     * Draws into the area, leaving everything outside it alone. Rendering may update what the
+     * widget shows, like where a list is scrolled to.
+     */
+    fn render(&mut self, screen: &mut Screen, area: &Rect);
+
+    /**
+     * Reacts to the input, given the area the widget was rendered in. Returns whether the input
+     * was used, so it can be offered to something else if not.
+     */
+    fn handle(&mut self, _input: &Input, _area: &Rect) -> bool {
+        false
+    }
+}
+
+/**
+ * Where text goes when it is narrower than its area.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/**
+ * Text that doesn't change, one line per row.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub text: String,
+    pub style: Style,
+    pub align: Align,
+}
+
+impl Label {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            style: Style::default(),
+            align: Align::Left,
+        }
+    }
+
+    pub fn style(self, style: Style) -> Self {
+        Self { style, ..self }
+    }
+
+    pub fn align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+}
+
+impl Widget for Label {
+    fn render(&mut self, screen: &mut Screen, area: &Rect) {
+        fill(screen, area, self.style);
+        for (row, line) in self
+            .text
+            .lines()
+            .enumerate()
+            .take(area.size.row.max(0) as usize)
+        {
+            let col = aligned(self.align, line, area.size.col);
+            write_clipped(
+                screen,
+                area,
+                Position::new(col, row as i32),
+                line,
+                self.style,
+            );
+        }
+    }
+}
+
+/**
+ * A single line of editable text. Typing inserts at the cursor, and the text scrolls to keep the
+ * cursor in view. Pasted text is inserted up to its first newline.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextInput {
+    pub text: String,
+    pub style: Style,
+    /// Whether the cursor is shown.
+    pub focused: bool,
+    /// In graphemes from the start.
+    cursor: usize,
+    scroll: usize,
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            style: Style::default(),
+            focused: true,
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+}
+
+impl TextInput {
+    pub fn new(text: &str) -> Self {
+        let mut input = Self::default();
+        input.set_text(text);
+        input
+    }
+
+    pub fn style(self, style: Style) -> Self {
+        Self { style, ..self }
+    }
+
+    /**
+     * Replaces the text, with the cursor at its end.
+     */
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.len();
+    }
+
+    /**
+     * The cursor, in graphemes from the start.
+     */
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    /**
+     * The byte index of the grapheme at the index, or the end.
+     */
+    fn byte_index(&self, index: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, text: &str) {
+        let at = self.byte_index(self.cursor);
+        let before = self.len();
+        self.text.insert_str(at, text);
+        // What was inserted may join the grapheme before it, like a combining mark does.
+        let after = self.len();
+        self.cursor = (self.cursor + after).saturating_sub(before).min(after);
+    }
+
+    /**
+     * Removes the grapheme at the index, if there is one.
+     */
+    fn remove(&mut self, index: usize) {
+        if index < self.len() {
+            let range = self.byte_index(index)..self.byte_index(index + 1);
+            self.text.replace_range(range, "");
+        }
+    }
+}
+
+impl Widget for TextInput {
+    fn render(&mut self, screen: &mut Screen, area: &Rect) {
+        // The text may have been changed directly.
+        self.cursor = self.cursor.min(self.len());
+        // Scrolled just enough to show the cursor, which may be after the end.
+        let widths: Vec<i32> = self
+            .text
+            .graphemes(true)
+            .chain(Some(" "))
+            .map(|grapheme| grapheme.width() as i32)
+            .collect();
+        self.scroll = self.scroll.min(self.cursor);
+        while self.scroll < self.cursor
+            && widths[self.scroll..=self.cursor].iter().sum::<i32>() > area.size.col
+        {
+            self.scroll += 1;
+        }
+
+        fill(screen, area, self.style);
+        let mut col = 0;
+        let graphemes = self.text.graphemes(true).chain(Some(" "));
+        for (i, grapheme) in graphemes.enumerate().skip(self.scroll) {
+            let style = if self.focused && i == self.cursor {
+                self.style.reverse()
+            } else {
+                self.style
+            };
+            let end = write_clipped(screen, area, Position::new(col, 0), grapheme, style);
+            if end == col {
+                break;
+            }
+            col = end;
+        }
+    }
+
+    fn handle(&mut self, input: &Input, _area: &Rect) -> bool {
+        let key = match input {
+            Input::Key(key) => key,
+            Input::Paste(text) => {
+                self.insert(text.lines().next().unwrap_or(""));
+                return true;
+            }
+            _ => return false,
+        };
+        match key.code {
+            KeyCode::Char(ch) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                self.insert(&ch.to_string())
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.remove(self.cursor);
+            }
+            KeyCode::Delete => self.remove(self.cursor),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/**
+ * Items one per row with one of them selected, scrolled to keep the selection in view. Arrows,
+ * paging keys and the mouse wheel move the selection, and clicking an item selects it.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct List {
+    pub items: Vec<String>,
+    pub style: Style,
+    pub selected_style: Style,
+    selected: usize,
+    offset: usize,
+}
+
+impl List {
+    pub fn new(items: Vec<String>) -> Self {
+        Self {
+            items,
+            style: Style::default(),
+            selected_style: Style::default().reverse(),
+            selected: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+    }
+
+    /**
+     * The first item shown.
+     */
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn step(&mut self, by: i32) {
+        self.select((self.selected as i32 + by).max(0) as usize);
+    }
+}
+
+impl Widget for List {
+    fn render(&mut self, screen: &mut Screen, area: &Rect) {
+        let rows = area.size.row.max(1) as usize;
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+
+        fill(screen, area, self.style);
+        for (row, item) in self.items.iter().skip(self.offset).take(rows).enumerate() {
+            let style = if row + self.offset == self.selected {
+                self.selected_style
+            } else {
+                self.style
+            };
+            let line = Rect::new(
+                area.origin + Position::new(0, row as i32),
+                Position::new(area.size.col, 1),
+            );
+            fill(screen, &line, style);
+            write_clipped(screen, &line, Position::new(0, 0), item, style);
+        }
+    }
+
+    fn handle(&mut self, input: &Input, area: &Rect) -> bool {
+        let page = area.size.row.max(1);
+        match input {
+            Input::Key(KeyEvent { code, .. }) => match code {
+                KeyCode::Up => self.step(-1),
+                KeyCode::Down => self.step(1),
+                KeyCode::PageUp => self.step(-page),
+                KeyCode::PageDown => self.step(page),
+                KeyCode::Home => self.select(0),
+                KeyCode::End => self.select(self.items.len()),
+                _ => return false,
+            },
+            Input::Scroll(rows, position, _) if area.contains(position) => self.step(*rows),
+            Input::Press(MouseButton::Left, position, _) if area.contains(position) => {
+                let index = self.offset + (position.row - area.origin.row) as usize;
+                if index >= self.items.len() {
+                    return false;
+                }
+                self.select(index);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/**
+ * How far along something is, as a bar across the first row of its area with the percentage,
+ * or a label, in the middle.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressBar {
+    /// From 0 to 1.
+    pub progress: f64,
+    pub label: Option<String>,
+    pub style: Style,
+    pub filled_style: Style,
+}
+
+impl ProgressBar {
+    pub fn new(progress: f64) -> Self {
+        Self {
+            progress,
+            label: None,
+            style: Style::default(),
+            filled_style: Style::default().reverse(),
+        }
+    }
+
+    pub fn label(self, label: &str) -> Self {
+        Self {
+            label: Some(label.to_string()),
+            ..self
+        }
+    }
+}
+
+impl Widget for ProgressBar {
+    fn render(&mut self, screen: &mut Screen, area: &Rect) {
+        let progress = self.progress.clamp(0.0, 1.0);
+        let label = match &self.label {
+            Some(label) => label.clone(),
+            None => format!("{:.0}%", progress * 100.0),
+        };
+        let bar = area.split_rows(1).0;
+        let (filled, empty) = bar.split_cols((progress * bar.size.col as f64).round() as i32);
+        fill(screen, &filled, self.filled_style);
+        fill(screen, &empty, self.style);
+
+        // The label takes the style of the part of the bar it is over.
+        let mut col = aligned(Align::Center, &label, bar.size.col);
+        for grapheme in label.graphemes(true) {
+            let style = if col < filled.size.col {
+                self.filled_style
+            } else {
+                self.style
+            };
+            col = write_clipped(screen, &bar, Position::new(col, 0), grapheme, style);
+        }
+    }
+}
+
+/**
+ * A row of text on both ends over a background, like the line at the bottom of an editor. The
+ * left text is cut short when both don't fit.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusBar {
+    pub left: String,
+    pub right: String,
+    pub style: Style,
+}
+
+impl StatusBar {
+    pub fn new(left: &str, right: &str) -> Self {
+        Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            style: Style::default().reverse(),
+        }
+    }
+
+    pub fn style(self, style: Style) -> Self {
+        Self { style, ..self }
+    }
+}
+
+impl Widget for StatusBar {
+    fn render(&mut self, screen: &mut Screen, area: &Rect) {
+        let row = area.split_rows(1).0;
+        fill(screen, &row, self.style);
+        let right = aligned(Align::Right, &self.right, row.size.col);
+        let (left_area, _) = row.split_cols(right);
+        write_clipped(
+            screen,
+            &left_area,
+            Position::new(0, 0),
+            &self.left,
+            self.style,
+        );
+        write_clipped(
+            screen,
+            &row,
+            Position::new(right, 0),
+            &self.right,
+            self.style,
+        );
+    }
+}
+
+/**
+ * Blanks the area with the style.
+ */
+fn fill(screen: &mut Screen, area: &Rect, style: Style) {
+    for position in area.intersect(&screen.bounds()).positions() {
+        screen.write_cell(&position, Cell::styled(' ', style));
+    }
+}
+
+/**
+ * The column text starts at to be aligned within the width.
+ */
+fn aligned(align: Align, text: &str, width: i32) -> i32 {
+    let space = (width - text.width() as i32).max(0);
+    match align {
+        Align::Left => 0,
+        Align::Center => space / 2,
+        Align::Right => space,
+    }
+}
+
+/**
+ * Writes the text on a row of the area from the position within it, leaving out the graphemes
+ * that don't fit. Returns the column after the last one written.
+ */
+fn write_clipped(screen: &mut Screen, area: &Rect, at: Position, text: &str, style: Style) -> i32 {
+    let clip = area.intersect(&screen.bounds());
+    let mut position = area.origin + at;
+    for grapheme in text.graphemes(true) {
+        let cell = Cell::with_grapheme(grapheme, style);
+        let width = cell.width() as i32;
+        if position.col + width > area.end().col {
+            break;
+        }
+        if clip.contains(&position) {
+            screen.write_cell(&position, cell);
+        }
+        position.col += width;
+    }
+    position.col - area.origin.col
+}
+
+#[test]
+fn test_label() {
+    let mut screen = Screen::with_size((8, 3).into());
+    crate::scrite!(&mut screen, (0, 0, 'x'), (7, 2, 'y'));
+    let area = Rect::new((1, 0).into(), (6, 2).into());
+
+    Label::new("hi\nthere you").render(&mut screen, &area);
+    Label::new("ok")
+        .align(Align::Right)
+        .render(&mut screen, &Rect::new((0, 2).into(), (6, 1).into()));
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "xhi\n there\n    ok y");
+
+    Label::new("mid")
+        .align(Align::Center)
+        .render(&mut screen, &area);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "x mid\n\n    ok y");
+}
+
+#[test]
+fn test_text_input() {
+    let key = |code: KeyCode| Input::Key(KeyEvent::new(code, KeyModifiers::NONE));
+    let area = Rect::new((0, 0).into(), (4, 1).into());
+    let mut input = TextInput::new("ab");
+
+    for event in [
+        key(KeyCode::Left),
+        key(KeyCode::Char('x')),
+        key(KeyCode::End),
+        key(KeyCode::Backspace),
+        Input::Paste("漢z\nignored".into()),
+        key(KeyCode::Home),
+        key(KeyCode::Delete),
+    ]
+    .iter()
+    {
+        assert!(input.handle(event, &area));
+    }
+    assert!(!input.handle(&key(KeyCode::Tab), &area));
+    assert_eq!(input.text, "x漢z");
+    assert_eq!(input.cursor(), 0);
+
+    let mut screen = Screen::with_size((5, 1).into());
+    input.render(&mut screen, &area);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "\x1b[7mx\x1b[0m漢z");
+
+    // Scrolled to show the cursor after the end.
+    input.handle(&key(KeyCode::End), &area);
+    input.render(&mut screen, &area);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "漢z\x1b[7m \x1b[0m");
+
+    // A combining mark joins the grapheme before the cursor.
+    let mut input = TextInput::new("e");
+    input.handle(&key(KeyCode::Char('\u{301}')), &area);
+    assert_eq!(input.text, "e\u{301}");
+    assert_eq!(input.cursor(), 1);
+    input.render(&mut screen, &area);
+    input.text.clear();
+    input.render(&mut screen, &area);
+    assert_eq!(input.cursor(), 0);
+}
+
+#[test]
+fn test_list() {
+    let key = |code: KeyCode| Input::Key(KeyEvent::new(code, KeyModifiers::NONE));
+    let items = ["one", "two", "three", "four", "five"];
+    let mut list = List::new(items.iter().map(|s| s.to_string()).collect());
+    let area = Rect::new((0, 1).into(), (5, 2).into());
+    let mut screen = Screen::with_size((5, 3).into());
+
+    assert!(list.handle(&key(KeyCode::Down), &area));
+    assert!(list.handle(&key(KeyCode::PageDown), &area));
+    assert_eq!(list.selected(), Some("four"));
+    list.render(&mut screen, &area);
+    assert_eq!(list.offset(), 2);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "\nthree\n\x1b[7mfour \x1b[0m");
+
+    let none = KeyModifiers::NONE;
+    assert!(list.handle(&Input::Press(MouseButton::Left, (1, 1).into(), none), &area));
+    assert_eq!(list.selected(), Some("three"));
+    assert!(!list.handle(&Input::Scroll(1, (1, 0).into(), none), &area));
+    assert!(list.handle(&Input::Scroll(9, (1, 2).into(), none), &area));
+    assert_eq!(list.selected(), Some("five"));
+    list.handle(&key(KeyCode::Home), &area);
+    list.render(&mut screen, &area);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "\n\x1b[7mone  \x1b[0m\ntwo");
+}
+
+#[test]
+fn test_progress_and_status() {
+    let mut screen = Screen::with_size((10, 2).into());
+    let bars = Rect::new((0, 0).into(), (10, 2).into()).split_rows(1);
+
+    ProgressBar::new(0.5).render(&mut screen, &bars.0);
+    StatusBar::new("left side", "right")
+        .style(Style::default())
+        .render(&mut screen, &bars.1);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "\x1b[7m   50\x1b[0m%\nleft right");
+
+    ProgressBar::new(2.0)
+        .label("done")
+        .render(&mut screen, &bars.0);
+    screen.flush();
+    crate::snapshot::assert_screen(&screen, "\x1b[7m   done   \x1b[0m\nleft right");
+}
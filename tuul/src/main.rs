@@ -1,8 +1,71 @@
 #![allow(dead_code)]
 #![allow(unused_macros)]
 
-use liib::term::free_draw;
+use std::path::Path;
+use std::process::exit;
 
+use liib::cast::{Cast, Player};
+use liib::keymap::Keymap;
+use liib::save::{load_file, save_file, Format};
+use liib::screen::Screen;
+use liib::term::{free_draw, record, terminal_out};
+
+/**
+ * Draws on the file given as the first argument, if any, which is loaded first when it exists
+ * and saved on quit. Files ending in `.ans` keep their colours. Key bindings are read from the
+ * file named by `TUUL_KEYMAP`, if set, and the session is recorded to the asciicast file named
+ * by `TUUL_RECORD`.
+ *
+ * `tuul --play <file> [speed]` replays a recording instead.
+ */
 fn main() {
-    free_draw().unwrap()
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--play") {
+        let path = match args.get(2) {
+            Some(path) => path,
+            None => {
+                eprintln!("Usage: tuul --play <file> [speed]");
+                exit(1);
+            }
+        };
+        let cast = match Cast::load_file(path) {
+            Ok(cast) => cast,
+            Err(e) => {
+                eprintln!("Can't load the recording {}: {}", path, e);
+                exit(1);
+            }
+        };
+        let speed = match args.get(3).map_or(Ok(1.0), |speed| speed.parse::<f64>()) {
+            Ok(speed) if speed > 0.0 => speed,
+            _ => {
+                eprintln!("The speed must be a number above 0, like 2 for twice as fast.");
+                exit(1);
+            }
+        };
+        Player::new(speed).play(&cast, &mut terminal_out()).unwrap();
+        return;
+    }
+
+    let path = args.get(1).cloned();
+    let screen = match &path {
+        Some(path) if Path::new(path).exists() => load_file(path).unwrap(),
+        _ => Screen::default(),
+    };
+
+    let mut keymap = match std::env::var_os("TUUL_KEYMAP") {
+        Some(path) => match Keymap::load_file(&path) {
+            Ok(keymap) => keymap,
+            Err(e) => {
+                eprintln!("Can't load the keymap {}: {}", path.to_string_lossy(), e);
+                exit(1);
+            }
+        },
+        None => Keymap::default(),
+    };
+
+    let _recording = std::env::var_os("TUUL_RECORD").map(|path| record(path).unwrap());
+    let screen = free_draw(screen, &mut keymap).unwrap();
+    if let Some(path) = path {
+        save_file(&screen, &path, Format::for_path(&path)).unwrap();
+    }
 }
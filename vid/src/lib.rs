@@ -1,26 +1,42 @@
-use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::time::Duration;
 
 use ffmpeg_next::{
-    format::{input, Pixel},
+    decoder,
+    format::{context::input::PacketIter, input, Pixel},
     media::Type,
     software::scaling::{context::Context, flag::Flags},
     util::frame::audio::Audio,
     util::frame::video::Video,
 };
 
-use liib::position::Position;
+use liib::app::{App, AppLoop, Flow};
+use liib::backend::{Backend, CrosstermBackend};
+use liib::compositor::Compositor;
+use liib::layout::{Constraint, Layout};
+use liib::position::{Position, Rect};
 use liib::ringer::Ringer;
-use liib::screen::Screen;
+use liib::screen::{Resize, Screen};
 
-use liib::term::{dump_ringer, dump_screen, get_size, make_room};
+use liib::term::{
+    dump_ringer, get_size, make_room, quit_key, record, resize_screen, terminal_input, Input,
+    Session,
+};
 
 pub struct Options {
     pub path: String,
     pub sound: bool,
     pub waveform: bool,
+    pub overlay: bool,
+    /// The asciicast file to record the playback to, if any.
+    pub record: Option<String>,
 }
 
+/**
+ * The frame rate to play at when the stream doesn't have one.
+ */
+const DEFAULT_FPS: f64 = 25.0;
+
 // pub fn term_screen(path: &str) -> Result<(), ffmpeg_next::Error> {
 pub fn term_screen(options: &Options) -> Result<(), ffmpeg_next::Error> {
     ffmpeg_next::init().unwrap();
@@ -46,167 +62,325 @@ pub fn handle_ictx(
         .best(Type::Video)
         .ok_or(ffmpeg_next::Error::StreamNotFound)?;
     let video_stream_index = input.index();
-    let mut decoder = input.codec().decoder().video()?;
-    let target_fps = {
-        let r = decoder.frame_rate().unwrap();
-        (r.numerator() as f32) / (r.denominator() as f32)
-    };
+    let decoder = input.codec().decoder().video()?;
+    // Not every stream knows its frame rate, and a broken one may say 0/0.
+    let target_fps = decoder
+        .frame_rate()
+        .map(f64::from)
+        .into_iter()
+        .chain(Some(f64::from(input.avg_frame_rate())))
+        .find(|fps| fps.is_finite() && *fps > 0.0)
+        .unwrap_or(DEFAULT_FPS);
     let size = get_size();
-    let mut scaler = Context::get(
-        decoder.format(),
-        decoder.width(),
-        decoder.height(),
-        Pixel::GRAY8,
-        size.col as u32,
-        size.row as u32,
-        // Flags::BILINEAR,
-        Flags::GAUSS,
-    )?;
+
+    // The waveform sits in a gutter to the left of the video.
+    let gutter = if options.waveform { 5 } else { 0 };
+    let layout = Layout::columns()
+        .pane("wave", Constraint::Fixed(gutter))
+        .pane("video", Constraint::Fill(1));
+    let panes = layout.solve(&Rect::new((0, 0).into(), size));
+    let scaler = scaler(&decoder, panes["video"].size)?;
 
     let audio_input = ictx
         .streams()
         .best(Type::Audio)
         .ok_or(ffmpeg_next::Error::StreamNotFound)?;
     let audio_stream_index = audio_input.index();
-    let mut audio_decoder = audio_input.codec().decoder().audio()?;
-
-    // println!(
-    //     "{}x{} -> {}x{} ({}) {}",
-    //     scaler.input().width,
-    //     scaler.input().height,
-    //     size.col,
-    //     size.row,
-    //     total_points,
-    //     decoder.format(),
-    // );
+    let audio_decoder = audio_input.codec().decoder().audio()?;
     println!("Audio: {:?}", audio_decoder.format());
 
-    // panic!("nope");
+    let recording = options
+        .record
+        .as_ref()
+        .map(|path| record(path).expect("could not start recording"));
+    // Raw mode, the alternate screen and a hidden cursor for the whole playback.
+    let session = Session::new().start().unwrap();
     make_room();
 
-    let mut frame_count: i32 = 0;
     let mut screen = Screen::with_size(size);
-    let total_points = size.col * size.row;
-
-    let time_start = Instant::now();
+    let mut compositor = Compositor::new();
+    compositor.add("video", panes["video"].origin, panes["video"].size, 0);
+    compositor.add("wave", panes["wave"].origin, panes["wave"].size, 1);
+
+    let mut playback = Playback {
+        options,
+        packets: ictx.packets(),
+        video_stream_index,
+        audio_stream_index,
+        decoder,
+        audio_decoder,
+        scaler,
+        layout,
+        panes,
+        compositor,
+        ringer: Ringer::new(),
+        packet_count: 0,
+        frame_count: 0,
+        audio_frame_count: 0,
+        eof: false,
+        error: None,
+    };
+    // Late frames are dropped to catch up with the sound.
+    let stats = AppLoop::new(target_fps)
+        .overlay(options.overlay)
+        .run(
+            &mut playback,
+            &mut screen,
+            &mut CrosstermBackend::stdout(),
+            terminal_input(),
+        )
+        .unwrap();
+    drop(session);
+    drop(recording);
+
+    if let Some(e) = playback.error {
+        return Err(e);
+    }
+    println!(
+        "Packets: {} | Frames: {} | Dropped: {}",
+        playback.packet_count, playback.frame_count, stats.dropped
+    );
+    Ok(())
+}
 
-    let mut process_video =
-        |decoder: &mut ffmpeg_next::decoder::Video| -> Result<(), ffmpeg_next::Error> {
-            let mut decoded = Video::empty();
-            while decoder.receive_frame(&mut decoded).is_ok() {
-                frame_count += 1;
+/// The audio levels mapped onto the height of the waveform.
+const AUDIO_RANGE: (f32, f32) = (0.00001, 0.01);
+
+/// The audio level above which the bell rings.
+const AUDIO_THRESHOLD: f32 = 0.0018;
+
+/**
+ * A video played on an [AppLoop]: each update decodes the next frame, which is rendered unless
+ * the loop has fallen behind.
+ */
+struct Playback<'a> {
+    options: &'a Options,
+    packets: PacketIter<'a>,
+    video_stream_index: usize,
+    audio_stream_index: usize,
+    decoder: decoder::Video,
+    audio_decoder: decoder::Audio,
+    scaler: Context,
+    layout: Layout,
+    panes: HashMap<String, Rect>,
+    compositor: Compositor,
+    ringer: Ringer,
+    packet_count: i32,
+    frame_count: i32,
+    audio_frame_count: i32,
+    /// Every packet was read and the decoder was told so.
+    eof: bool,
+    /// What stopped playback early, which the loop itself can't return.
+    error: Option<ffmpeg_next::Error>,
+}
 
+impl<'a> Playback<'a> {
+    /**
+     * Decodes the next frame into the video pane, reading packets until there is one. Returns
+     * false once the video is over.
+     */
+    fn next_frame(&mut self) -> Result<bool, ffmpeg_next::Error> {
+        let mut decoded = Video::empty();
+        loop {
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                self.frame_count += 1;
                 let mut frame = Video::empty();
-                scaler.run(&decoded, &mut frame)?;
-
-                let plane = frame.plane::<image::Luma<u8>>(0);
-
-                /*
-                Apparently the plane contains spacer data points, like this:
-                    00 01 02 03 04 05 __
-                    07 08 09 10 11 12 __
-                    14 15 16 17 18 19 __
-                    21 22 23 34 25 26
-                If we modulo by the desired column width, lines are skewed:
-                    00 01 02 03 04 05
-                    __ 07 08 09 10 11
-                    12 __ 14 15 16 17
-                    18 19 __ 21 22 23
-                    34 25 26
-                So the actual number of columns is found by dividing the excess
-                accross each row. The screen will disregard the columns that are
-                out of bounds.
-                */
-
-                let points = plane.len();
-                let excess_points = points as i32 - total_points;
-                let real_cols = size.col + (excess_points / size.row);
-                let calc_pos = |index: i32| -> Position {
-                    let c = index % real_cols;
-                    let r = index / real_cols;
-                    if options.waveform {
-                        (c + 5, r).into()
-                    } else {
-                        (c, r).into()
-                    }
-                };
+                self.scaler.run(&decoded, &mut frame)?;
+                let size = self.panes["video"].size;
+                draw_plane(
+                    self.compositor.screen("video").unwrap(),
+                    frame.data(0),
+                    size,
+                );
+                return Ok(true);
+            }
+            if self.eof {
+                return Ok(false);
+            }
 
-                for (i, point) in plane.iter().enumerate() {
-                    let pos = calc_pos(i as i32);
-                    let data = point.data[0];
-                    let ch = data.to_char();
-                    screen.write(&pos, ch);
+            match self.packets.next() {
+                Some((stream, packet)) => {
+                    if stream.index() == self.video_stream_index {
+                        self.packet_count += 1;
+                        self.decoder.send_packet(&packet)?;
+                    } else if stream.index() == self.audio_stream_index {
+                        self.audio_decoder.send_packet(&packet)?;
+                        self.process_audio();
+                    }
                 }
-
-                let time_now = Instant::now();
-                let time_elapsed = time_now - time_start;
-                let expected_elapsed = Duration::from_secs_f32(frame_count as f32 / target_fps);
-                if time_elapsed < expected_elapsed {
-                    sleep(expected_elapsed - time_elapsed)
+                None => {
+                    self.decoder.send_eof()?;
+                    self.eof = true;
                 }
-                dump_screen(&mut screen).unwrap();
             }
-            Ok(())
-        };
-
-    let mut ringer = Ringer::new();
-    let mut wave_screen = Screen::with_size((5, size.row).into());
-    let mut audio_frame_count: i32 = 0;
-    let audio_range = (0.00001, 0.01);
-    let audio_threshold = //
-        // --
-        0.0018
-    ;
-    let mut process_audio =
-        |decoder: &mut ffmpeg_next::decoder::Audio| -> Result<(), ffmpeg_next::Error> {
-            let mut decoded = Audio::empty();
-            while decoder.receive_frame(&mut decoded).is_ok() {
-                audio_frame_count += 1;
-
-                let point: f32 = decoded.plane::<f32>(0).iter().sum::<f32>()
-                    / decoded.plane::<f32>(0).len() as f32;
-                if audio_threshold < point {
-                    ringer.ring();
-                }
+        }
+    }
 
-                if options.sound {
-                    dump_ringer(&mut ringer).unwrap();
-                }
+    /**
+     * Rings the bell on loud audio and draws its level in the waveform.
+     */
+    fn process_audio(&mut self) {
+        let mut decoded = Audio::empty();
+        while self.audio_decoder.receive_frame(&mut decoded).is_ok() {
+            self.audio_frame_count += 1;
+
+            let point: f32 =
+                decoded.plane::<f32>(0).iter().sum::<f32>() / decoded.plane::<f32>(0).len() as f32;
+            if AUDIO_THRESHOLD < point {
+                self.ringer.ring();
+            }
 
-                let level: i32 = (((point - audio_range.0) / (audio_range.1 - audio_range.0))
-                    * (wave_screen.rows as f32)) as i32;
+            if self.options.sound {
+                dump_ringer(&mut self.ringer).unwrap();
+            }
 
-                if audio_frame_count % 5 == 0 {
-                    wave_screen.clear();
-                }
+            let wave_screen = self.compositor.screen("wave").unwrap();
+            let level: i32 = (((point - AUDIO_RANGE.0) / (AUDIO_RANGE.1 - AUDIO_RANGE.0))
+                * (wave_screen.rows as f32)) as i32;
+
+            if self.audio_frame_count % 5 == 0 {
+                wave_screen.clear();
+            }
 
-                wave_screen.write(&(audio_frame_count % wave_screen.cols, level).into(), 'O');
-                if options.waveform {
-                    dump_screen(&mut wave_screen).unwrap();
+            if self.options.waveform {
+                wave_screen.write(
+                    &(self.audio_frame_count % wave_screen.cols, level).into(),
+                    'O',
+                );
+            }
+
+            self.audio_decoder.flush();
+        }
+    }
+
+    /**
+     * Lays the panes out again for the terminal's new size, and scales frames to fit.
+     */
+    fn resize(&mut self, size: Position, screen: &mut Screen) -> Result<(), ffmpeg_next::Error> {
+        self.panes = self.layout.solve(&Rect::new((0, 0).into(), size));
+        self.scaler = scaler(&self.decoder, self.panes["video"].size)?;
+        resize_screen(screen, size, Resize::Crop);
+        self.compositor.arrange(&self.panes);
+        Ok(())
+    }
+}
+
+impl<'a, B: Backend> App<B> for Playback<'a> {
+    fn input(
+        &mut self,
+        input: Input,
+        screen: &mut Screen,
+        _backend: &mut B,
+    ) -> crossterm::Result<Flow> {
+        match input {
+            Input::Key(key) if quit_key(&key) => return Ok(Flow::Quit),
+            Input::Resize(size) => {
+                if let Err(e) = self.resize(size, screen) {
+                    self.error = Some(e);
+                    return Ok(Flow::Quit);
                 }
+            }
+            _ => {}
+        }
+        Ok(Flow::Continue)
+    }
 
-                decoder.flush();
+    fn update(&mut self, _step: Duration, _screen: &mut Screen) -> Flow {
+        match self.next_frame() {
+            Ok(true) => Flow::Continue,
+            Ok(false) => Flow::Quit,
+            Err(e) => {
+                self.error = Some(e);
+                Flow::Quit
             }
-            Ok(())
-        };
-
-    let mut packet_count = 0;
-    for (stream, packet) in ictx.packets() {
-        if stream.index() == video_stream_index {
-            packet_count += 1;
-
-            decoder.send_packet(&packet).unwrap();
-            process_video(&mut decoder)?;
-        } else if stream.index() == audio_stream_index {
-            audio_decoder.send_packet(&packet).unwrap();
-            process_audio(&mut audio_decoder)?;
         }
     }
-    decoder.send_eof()?;
-    process_video(&mut decoder)?;
 
-    println!("Packets: {} | Frames: {}", packet_count, frame_count);
-    Ok(())
+    fn render(&mut self, screen: &mut Screen) {
+        self.compositor.compose(screen);
+    }
+}
+
+/**
+ * Scales the decoder's frames to one grey point per cell of the size.
+ */
+fn scaler(decoder: &decoder::Video, size: Position) -> Result<Context, ffmpeg_next::Error> {
+    Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        Pixel::GRAY8,
+        size.col as u32,
+        size.row as u32,
+        Flags::GAUSS,
+    )
+}
+
+/**
+ * Draws a grayscale plane scaled to the size onto the screen, one character per point.
+ */
+pub fn draw_plane(screen: &mut Screen, plane: &[u8], size: Position) {
+    /*
+    Apparently the plane contains spacer data points, like this:
+        00 01 02 03 04 05 __
+        07 08 09 10 11 12 __
+        14 15 16 17 18 19 __
+        21 22 23 34 25 26
+    If we modulo by the desired column width, lines are skewed:
+        00 01 02 03 04 05
+        __ 07 08 09 10 11
+        12 __ 14 15 16 17
+        18 19 __ 21 22 23
+        34 25 26
+    So the actual number of columns is found by dividing the excess
+    accross each row. The screen will disregard the columns that are
+    out of bounds.
+    */
+
+    let total_points = size.col * size.row;
+    let points = plane.len();
+    let excess_points = points as i32 - total_points;
+    let real_cols = size.col + (excess_points / size.row);
+    let calc_pos = |index: i32| -> Position {
+        let c = index % real_cols;
+        let r = index / real_cols;
+        (c, r).into()
+    };
+
+    for (i, data) in plane.iter().enumerate() {
+        let pos = calc_pos(i as i32);
+        let ch = data.to_char();
+        screen.write(&pos, ch);
+    }
+}
+
+#[test]
+fn test_draw_plane() {
+    use liib::backend::MemoryBackend;
+    use liib::save::to_text;
+    use liib::term::draw_screen;
+
+    let layout = Layout::columns()
+        .pane("wave", Constraint::Fixed(5))
+        .pane("video", Constraint::Fill(1));
+    let panes = layout.solve(&Rect::from((8, 2)));
+    let size = panes["video"].size;
+    assert_eq!(size, (3, 2).into());
+
+    let mut compositor = Compositor::new();
+    compositor.add("video", panes["video"].origin, size, 0);
+    let wave = compositor.add("wave", panes["wave"].origin, panes["wave"].size, 1);
+    wave.write(&(1, 1).into(), 'O');
+
+    // Each row is padded with a spacer point.
+    let plane = [240, 0, 240, 9, 63, 240, 63, 9];
+    draw_plane(compositor.screen("video").unwrap(), &plane, size);
+
+    let mut screen = Screen::with_size((8, 2).into());
+    let mut backend = MemoryBackend::with_size((8, 2).into());
+    compositor.compose(&mut screen);
+    draw_screen(&mut screen, &mut backend).unwrap();
+    assert_eq!(to_text(backend.screen()), "     8 8\n O   -8-\n");
 }
 
 trait GrayText {
@@ -26,6 +26,18 @@ fn app() {
                 .short("w")
                 .help("Display a crude audio waveform next to the video"),
         )
+        .arg(
+            Arg::with_name("overlay")
+                .short("f")
+                .help("Display the frame rate and dropped frames"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .short("r")
+                .takes_value(true)
+                .value_name("file")
+                .help("Record the playback to an asciicast file"),
+        )
         .get_matches();
 
     let path = matches.value_of("path").unwrap_or("");
@@ -33,11 +45,15 @@ fn app() {
 
     let sound = matches.occurrences_of("sound") >= 1;
     let waveform = matches.occurrences_of("waveform") >= 1;
+    let overlay = matches.occurrences_of("overlay") >= 1;
+    let record = matches.value_of("record").map(str::to_string);
 
     let options = Options {
         path: path.to_string(),
         sound,
         waveform,
+        overlay,
+        record,
     };
     term_screen(&options).unwrap();
 }